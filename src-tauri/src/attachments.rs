@@ -0,0 +1,357 @@
+use crate::error::{VaultError, VaultResult};
+use crate::graph::is_hidden_path;
+use crate::index::{file_stamp, LinkIndexStore};
+use crate::trash::is_in_trash;
+use crate::vault::{
+    is_markdown_file, mime_for_path, path_to_rel_string, resolve_folder_path_for_create,
+    resolve_vault,
+};
+use crate::walk::{is_ignore_file, walk_vault};
+use crate::wikilinks::{
+    parse_markdown_images, parse_markdown_links, parse_wikilinks, resolve_destination_path,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+/// Folder imported attachments go to unless another one is configured.
+pub const DEFAULT_ATTACHMENTS_FOLDER: &str = "attachments";
+
+/// Upper bound on ` 1`, ` 2`, ... suffixes tried for a taken file name.
+const MAX_NAME_ATTEMPTS: usize = 10_000;
+
+/// A file copied into the vault.
+#[derive(Debug, Serialize)]
+pub struct ImportedAttachment {
+    pub rel_path: String,
+    /// Embed to insert into the note, e.g. `![[attachments/image.png]]`
+    pub link: String,
+}
+
+/// A non-Markdown file in the vault.
+#[derive(Debug, Serialize)]
+pub struct Attachment {
+    pub rel_path: String,
+    pub size: u64,
+    pub mtime_ms: Option<u64>,
+    pub mime: String,
+    pub is_hidden: bool,
+    /// Notes embedding or linking to the file, in rel_path order
+    pub referenced_by: Vec<String>,
+}
+
+fn note_dir(note_rel_path: &str) -> &str {
+    note_rel_path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// Split a file name into stem and extension, e.g. `a.b.png` into `a.b` and
+/// `.png`. Dotfiles have no extension.
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(idx) if idx > 0 => (&name[..idx], &name[idx..]),
+        _ => (name, ""),
+    }
+}
+
+/// Vault-relative folder for attachments of the note at `note_rel_path`.
+/// Folders starting with `./` are relative to the note's folder.
+fn attachment_folder(folder: &str, note_rel_path: Option<&str>) -> String {
+    let folder = folder.trim().replace('\\', "/");
+    let relative = folder.strip_prefix("./").or((folder == ".").then_some(""));
+    let Some(relative) = relative else {
+        return folder.trim_matches('/').to_string();
+    };
+    let base = note_rel_path.map_or("", note_dir);
+    [base, relative.trim_matches('/')]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Embed for `rel_path` as written in the note at `note_rel_path`: relative
+/// when the file sits in or below the note's folder, vault-absolute
+/// otherwise, matching how embeds are resolved for previews.
+fn embed_link(rel_path: &str, note_rel_path: Option<&str>) -> String {
+    let relative = note_rel_path.and_then(|note| match note_dir(note) {
+        "" => Some(rel_path),
+        dir => rel_path.strip_prefix(&format!("{dir}/")),
+    });
+    match relative {
+        Some(relative) => format!("![[{relative}]]"),
+        None => format!("![[/{rel_path}]]"),
+    }
+}
+
+/// Copy `bytes` into the attachments `folder` (by default
+/// `DEFAULT_ATTACHMENTS_FOLDER`) as `file_name`, adding a ` 1`, ` 2`, ...
+/// suffix when the name is taken. Returns the new file and the embed to
+/// insert into the note at `note_rel_path`.
+pub fn import_attachment_impl(
+    vault_path: &str,
+    note_rel_path: Option<&str>,
+    file_name: &str,
+    bytes: &[u8],
+    folder: Option<&str>,
+) -> VaultResult<ImportedAttachment> {
+    let vault = resolve_vault(vault_path)?;
+
+    // Keep only the file name, without characters that break wikilinks.
+    let name = file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or("")
+        .trim()
+        .replace(['[', ']', '|', '#', '^'], "-");
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(VaultError::Invalid("attachment name is empty".to_string()));
+    }
+    if is_markdown_file(Path::new(&name)) {
+        return Err(VaultError::Invalid(
+            "markdown files are notes, not attachments".to_string(),
+        ));
+    }
+
+    let folder = attachment_folder(folder.unwrap_or(DEFAULT_ATTACHMENTS_FOLDER), note_rel_path);
+    let dir = if folder.is_empty() {
+        vault.clone()
+    } else {
+        resolve_folder_path_for_create(vault_path, &folder)?
+    };
+    if is_in_trash(&path_to_rel_string(&vault, &dir)?) {
+        return Err(VaultError::Invalid(
+            "attachments cannot be imported into the trash".to_string(),
+        ));
+    }
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| VaultError::io(e, "failed to create attachments folder"))?;
+
+    let (stem, ext) = split_extension(&name);
+    for n in 0..MAX_NAME_ATTEMPTS {
+        let candidate = if n == 0 {
+            name.clone()
+        } else {
+            format!("{stem} {n}{ext}")
+        };
+        let path = dir.join(&candidate);
+        // `create_new` claims the name atomically, so concurrent imports
+        // never overwrite each other.
+        let mut file = match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(VaultError::io(e, "failed to create attachment")),
+        };
+        if let Err(e) = file.write_all(bytes).and_then(|_| file.sync_all()) {
+            drop(file);
+            let _ = std::fs::remove_file(&path);
+            return Err(VaultError::io(e, "failed to write attachment"));
+        }
+
+        let rel_path = path_to_rel_string(&vault, &path)?;
+        return Ok(ImportedAttachment {
+            link: embed_link(&rel_path, note_rel_path),
+            rel_path,
+        });
+    }
+
+    Err(VaultError::AlreadyExists(
+        "too many attachments with this name".to_string(),
+    ))
+}
+
+/// Attachments by lowercased rel_path and file name, for matching links.
+struct AttachmentLookup {
+    keys: Vec<String>,
+    paths: HashMap<String, usize>,
+    names: HashMap<String, Vec<usize>>,
+}
+
+impl AttachmentLookup {
+    fn new(attachments: &[Attachment]) -> Self {
+        let mut lookup = AttachmentLookup {
+            keys: Vec::new(),
+            paths: HashMap::new(),
+            names: HashMap::new(),
+        };
+        for (idx, attachment) in attachments.iter().enumerate() {
+            let key = attachment.rel_path.to_lowercase();
+            let name = key.rsplit('/').next().unwrap_or(&key).to_string();
+            lookup.names.entry(name).or_default().push(idx);
+            lookup.paths.insert(key.clone(), idx);
+            lookup.keys.push(key);
+        }
+        lookup
+    }
+
+    fn by_path(&self, rel_path: Option<String>) -> Option<usize> {
+        self.paths.get(&rel_path?.to_lowercase()).copied()
+    }
+
+    /// Attachments a wikilink target may mean: a path relative to the note
+    /// or the vault root, or a file name or path suffix anywhere in the
+    /// vault. Every candidate counts, so nothing in use is reported as an
+    /// orphan.
+    fn wikilink_targets(&self, note: &str, target: &str) -> Vec<usize> {
+        let target = target.trim().replace('\\', "/");
+        if target.starts_with('/') {
+            return self
+                .by_path(resolve_destination_path(note, &target))
+                .into_iter()
+                .collect();
+        }
+
+        let lower = target.to_lowercase();
+        let suffix = format!("/{lower}");
+        let name = lower.rsplit('/').next().unwrap_or(&lower);
+        let mut found: Vec<usize> = self
+            .names
+            .get(name)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|&idx| self.keys[idx] == lower || self.keys[idx].ends_with(&suffix))
+            .collect();
+        found.extend(self.by_path(resolve_destination_path(note, &target)));
+        found
+    }
+
+    /// Attachments embedded or linked from `text`, written in the note at
+    /// `note`.
+    fn referenced_from(&self, note: &str, text: &str) -> HashSet<usize> {
+        let mut found: HashSet<usize> = parse_wikilinks(text)
+            .iter()
+            .flat_map(|link| self.wikilink_targets(note, &link.target))
+            .collect();
+        let markdown = parse_markdown_links(text)
+            .into_iter()
+            .chain(parse_markdown_images(text));
+        found
+            .extend(markdown.filter_map(|link| {
+                self.by_path(resolve_destination_path(note, &link.destination))
+            }));
+        found
+    }
+}
+
+/// Every non-Markdown file in the vault with the notes referencing it, in
+/// rel_path order. Ignore files are left out.
+pub fn list_attachments_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    show_hidden: bool,
+) -> VaultResult<Vec<Attachment>> {
+    let vault = resolve_vault(vault_path)?;
+    let walk = walk_vault(&vault, |path| {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        !is_markdown_file(path) && !is_ignore_file(name)
+    });
+    for warning in &walk.warnings {
+        log::warn!("skipped while listing attachments: {warning}");
+    }
+
+    let mut attachments: Vec<Attachment> = Vec::new();
+    for path in &walk.files {
+        let rel_path = path_to_rel_string(&vault, path)?;
+        let is_hidden = is_hidden_path(&rel_path);
+        if is_hidden && !show_hidden {
+            continue;
+        }
+        let stamp = file_stamp(path);
+        attachments.push(Attachment {
+            size: stamp.map_or(0, |(_, size, _)| size),
+            mtime_ms: stamp.map(|(mtime_ms, _, _)| mtime_ms),
+            mime: mime_for_path(path),
+            is_hidden,
+            referenced_by: Vec::new(),
+            rel_path,
+        });
+    }
+
+    // Hidden notes count as references too, so nothing in use looks unused.
+    let notes: Vec<String> = store.with_index(vault_path, |_, index| {
+        index.notes().map(|(note, _)| note.clone()).collect()
+    })?;
+    let lookup = AttachmentLookup::new(&attachments);
+    for note in notes {
+        let text = match std::fs::read_to_string(vault.join(&note)) {
+            Ok(text) => text,
+            Err(e) => {
+                log::warn!("failed to read attachment links in {note}: {e}");
+                continue;
+            }
+        };
+        for idx in lookup.referenced_from(&note, &text) {
+            attachments[idx].referenced_by.push(note.clone());
+        }
+    }
+
+    Ok(attachments)
+}
+
+/// Attachments no note embeds or links to, in rel_path order.
+pub fn find_orphan_attachments_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    show_hidden: bool,
+) -> VaultResult<Vec<Attachment>> {
+    let mut attachments = list_attachments_impl(store, vault_path, show_hidden)?;
+    attachments.retain(|attachment| attachment.referenced_by.is_empty());
+    Ok(attachments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{attachment_folder, find_orphan_attachments_impl, import_attachment_impl};
+    use crate::index::LinkIndexStore;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn attachment_folders() {
+        assert_eq!(
+            attachment_folder("attachments", Some("a/b/Note.md")),
+            "attachments"
+        );
+        assert_eq!(attachment_folder("./", Some("a/b/Note.md")), "a/b");
+        assert_eq!(attachment_folder("./img/", Some("a/Note.md")), "a/img");
+        assert_eq!(attachment_folder("./img", Some("Note.md")), "img");
+        assert_eq!(attachment_folder("/", None), "");
+    }
+
+    #[test]
+    fn imports_and_finds_orphans() {
+        let dir = TempDir::new("attachments");
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+
+        let import = |name: &str, folder: Option<&str>| {
+            import_attachment_impl(&vault, Some("notes/Note.md"), name, b"png", folder).unwrap()
+        };
+        let first = import("shot.png", None);
+        let second = import("C:\\tmp\\shot.png", None);
+        let local = import("diagram [v2].png", Some("./"));
+        assert_eq!(first.link, "![[/attachments/shot.png]]");
+        assert_eq!(second.rel_path, "attachments/shot 1.png");
+        assert_eq!(local.link, "![[diagram -v2-.png]]");
+        std::fs::write(dir.join("notes/unused.pdf"), "pdf").unwrap();
+
+        std::fs::write(
+            dir.join("notes/Note.md"),
+            "![[diagram -v2-.png]] ![shot](../attachments/shot%201.png) `![[unused.pdf]]`",
+        )
+        .unwrap();
+        std::fs::write(dir.join("Index.md"), "![[shot.png|200]]").unwrap();
+
+        let orphans: Vec<String> = find_orphan_attachments_impl(&store, &vault, false)
+            .unwrap()
+            .into_iter()
+            .map(|attachment| attachment.rel_path)
+            .collect();
+        assert_eq!(orphans, vec!["notes/unused.pdf"]);
+    }
+}
@@ -1,85 +1,191 @@
-use crate::vault::{list_markdown_files_impl, read_note_impl};
-use std::collections::HashSet;
+use crate::error::VaultResult;
+use crate::index::{rel_path_key, LinkIndexStore};
+use crate::search::snippet_window;
+use crate::wikilinks::{
+    normalize_wikilink_target, parse_markdown_links, parse_wikilinks, resolve_markdown_destination,
+};
+use serde::Serialize;
+use std::ops::Range;
 
-fn extract_wikilinks(text: &str) -> Vec<String> {
-    let mut links = Vec::new();
-    let mut seen = HashSet::new();
-    let mut idx = 0;
-    while let Some(start) = text[idx..].find("[[") {
-        let start = idx + start + 2;
-        if let Some(end) = text[start..].find("]]") {
-            let end = start + end;
-            let raw = &text[start..end];
-            let target = raw.split('|').next().unwrap_or("");
-            let normalized = normalize_wikilink_target(target);
-            if !normalized.is_empty() {
-                if seen.insert(normalized.clone()) {
-                    links.push(normalized);
-                }
-            }
-            idx = end + 2;
-        } else {
-            break;
-        }
-    }
-    links
+/// One link from another note to the target note.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BacklinkOccurrence {
+    /// 1-based line number of the link
+    pub line: usize,
+    /// Byte range of the link in the linking note
+    pub range: Range<usize>,
+    /// Paragraph around the link, cut down to a readable length
+    pub snippet: String,
+    /// Byte range of the link within `snippet`
+    pub highlight: Range<usize>,
+    /// Display text of the link (`[[Note|alias]]` or `[alias](Note.md)`)
+    pub alias: Option<String>,
+    /// Whether the link is an `![[embed]]`
+    pub is_embed: bool,
 }
 
-fn normalize_wikilink_target(target: &str) -> String {
-    let raw = target.split('|').next().unwrap_or("");
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return String::new();
-    }
+/// A note linking to the target note, with every link in document order.
+#[derive(Debug, Serialize)]
+pub struct Backlink {
+    pub rel_path: String,
+    pub occurrences: Vec<BacklinkOccurrence>,
+}
 
-    let mut s = trimmed.to_string();
-    if s.to_ascii_lowercase().ends_with(".md") {
-        s.truncate(s.len().saturating_sub(3));
+/// Byte range of the paragraph (run of non-blank lines) containing `offset`.
+fn paragraph_range(text: &str, offset: usize) -> Range<usize> {
+    let mut start = 0;
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        let line_start = pos;
+        pos += line.len();
+        if !line.trim().is_empty() {
+            continue;
+        }
+        if line_start > offset {
+            return start..line_start;
+        }
+        start = pos;
     }
+    start..text.len()
+}
 
-    s.to_ascii_lowercase()
+/// Snippet of the paragraph around the link at `range`, on a single line,
+/// with the link's position inside it.
+pub(crate) fn occurrence_snippet(text: &str, range: &Range<usize>) -> (String, Range<usize>) {
+    let paragraph = paragraph_range(text, range.start);
+    // Newlines become spaces so byte offsets stay valid.
+    let flat = text[paragraph.clone()].replace(['\r', '\n'], " ");
+    let focus = range.start - paragraph.start;
+    let (from, to) = snippet_window(&flat, focus);
+    let slice = &flat[from..to];
+    let trimmed = slice.trim_start();
+    let offset = from + (slice.len() - trimmed.len());
+    let trimmed = trimmed.trim_end();
+
+    let start = focus.saturating_sub(offset).min(trimmed.len());
+    let end = (range.end - paragraph.start)
+        .saturating_sub(offset)
+        .min(trimmed.len());
+    (trimmed.to_string(), start..end)
 }
 
-pub fn find_backlinks_impl(vault_path: &str, target_title: &str) -> Result<Vec<String>, String> {
-    let files = list_markdown_files_impl(vault_path)?;
-    let mut backlinks: Vec<String> = Vec::new();
+/// Every link in `text` written in the note at `rel_path` whose normalized
+/// target is accepted by `points_here`.
+fn find_occurrences(
+    rel_path: &str,
+    text: &str,
+    points_here: impl Fn(&str) -> bool,
+) -> Vec<BacklinkOccurrence> {
+    let wikilinks = parse_wikilinks(text)
+        .into_iter()
+        .filter(|link| points_here(&link.normalized_target()))
+        .map(|link| (link.line, link.range, link.alias, link.is_embed));
+    let markdown = parse_markdown_links(text)
+        .into_iter()
+        .filter(|link| {
+            resolve_markdown_destination(rel_path, &link.destination)
+                .is_some_and(|path| points_here(&format!("/{}", rel_path_key(&path))))
+        })
+        .map(|link| {
+            let alias = Some(link.text.trim().to_string()).filter(|t| !t.is_empty());
+            (link.line, link.range, alias, false)
+        });
 
+    let mut occurrences: Vec<BacklinkOccurrence> = wikilinks
+        .chain(markdown)
+        .map(|(line, range, alias, is_embed)| {
+            let (snippet, highlight) = occurrence_snippet(text, &range);
+            BacklinkOccurrence {
+                line,
+                range,
+                snippet,
+                highlight,
+                alias,
+                is_embed,
+            }
+        })
+        .collect();
+    occurrences.sort_by_key(|occurrence| occurrence.range.start);
+    occurrences
+}
+
+/// Notes linking to the note titled `target_title`. With `target_rel_path`,
+/// links are matched against that exact note, so duplicate titles in other
+/// folders do not mix in.
+pub fn find_backlinks_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    target_title: &str,
+    target_rel_path: Option<&str>,
+) -> VaultResult<Vec<Backlink>> {
     let target_title = normalize_wikilink_target(target_title);
     if target_title.is_empty() {
-        return Ok(backlinks);
+        return Ok(Vec::new());
     }
 
-    for file in files {
-        let text = match read_note_impl(vault_path, &file.rel_path) {
-            Ok(t) => t,
-            Err(_) => continue,
+    let (vault, resolver, resolved, sources) = store.with_index(vault_path, |vault, index| {
+        // Resolve the title first so links through paths and aliases count too.
+        let resolver = index.resolver();
+        let resolved = match target_rel_path {
+            Some(rel_path) => index.note(rel_path).map(|_| rel_path.to_string()),
+            None => resolver.resolve("", &target_title).map(str::to_string),
         };
-        let links = extract_wikilinks(&text);
-        if links.iter().any(|l| l == &target_title) {
-            backlinks.push(file.rel_path);
-        }
-    }
+        let sources = match &resolved {
+            Some(rel_path) => index.resolved_backlinks(rel_path),
+            None => index.backlinks(&target_title),
+        };
+        (vault.to_path_buf(), resolver, resolved, sources)
+    })?;
 
-    backlinks.sort();
-    Ok(backlinks)
+    Ok(sources
+        .into_iter()
+        .map(|rel_path| {
+            let points_here = |target: &str| match &resolved {
+                Some(resolved) => resolver.resolve(&rel_path, target) == Some(resolved.as_str()),
+                None => target == target_title,
+            };
+            let occurrences = match std::fs::read_to_string(vault.join(&rel_path)) {
+                Ok(text) => find_occurrences(&rel_path, &text, points_here),
+                Err(e) => {
+                    log::warn!("failed to read backlinks in {rel_path}: {e}");
+                    Vec::new()
+                }
+            };
+            Backlink {
+                rel_path,
+                occurrences,
+            }
+        })
+        .collect())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{extract_wikilinks, normalize_wikilink_target};
+    use super::{find_occurrences, occurrence_snippet};
 
     #[test]
-    fn normalize_wikilinks() {
-        assert_eq!(normalize_wikilink_target("  Note Name  "), "note name");
-        assert_eq!(normalize_wikilink_target(" Note | Alias "), "note");
-        assert_eq!(normalize_wikilink_target("Foo.md"), "foo");
-        assert_eq!(normalize_wikilink_target("Foo.MD"), "foo");
-        assert_eq!(normalize_wikilink_target(""), "");
+    fn snippets_cover_the_paragraph() {
+        let text = "# Title\n\nFirst line with\n[[Target|shown]] in it.\n\nNext paragraph.\n";
+        let start = text.find("[[").unwrap();
+        let range = start..start + "[[Target|shown]]".len();
+        let (snippet, highlight) = occurrence_snippet(text, &range);
+        assert_eq!(snippet, "First line with [[Target|shown]] in it.");
+        assert_eq!(&snippet[highlight], "[[Target|shown]]");
     }
 
     #[test]
-    fn extract_links() {
-        let links = extract_wikilinks("[[Foo]] [[ foo ]] [[FOO|bar]]");
-        assert_eq!(links, vec!["foo".to_string()]);
+    fn finds_wiki_and_markdown_occurrences() {
+        let text = "See [[Target]] and [again](../Target.md).\n\n`[[Target]]` ![[target#Part]]\n";
+        let occurrences =
+            find_occurrences("sub/Note.md", text, |t| t == "target" || t == "/target");
+        let found: Vec<(usize, Option<&str>, bool)> = occurrences
+            .iter()
+            .map(|o| (o.line, o.alias.as_deref(), o.is_embed))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(1, None, false), (1, Some("again"), false), (3, None, true)]
+        );
+        assert_eq!(occurrences[2].snippet, "`[[Target]]` ![[target#Part]]");
     }
 }
@@ -0,0 +1,146 @@
+use crate::vault::NoteContents;
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::io::ErrorKind;
+
+/// Error returned by every vault command.
+///
+/// Serialized as `{ "code": ..., "message": ... }` plus variant data. Codes
+/// are stable so the frontend can match on them; messages are English
+/// fallbacks for logs and unlocalized UI.
+#[derive(Debug)]
+pub enum VaultError {
+    /// The note, folder or entry does not exist.
+    NotFound(String),
+    /// Something already exists at the target path.
+    AlreadyExists(String),
+    /// The path points outside the vault.
+    EscapesVault(String),
+    /// The path is not a Markdown note.
+    NotMarkdown(String),
+    /// The OS refused access, or the vault is on a read-only filesystem.
+    PermissionDenied(String),
+    /// The note changed on disk since the expected version was read.
+    Conflict(NoteContents),
+    /// The request itself is malformed, e.g. an unsupported path component.
+    Invalid(String),
+    /// Any other I/O or internal failure.
+    Io { kind: ErrorKind, message: String },
+}
+
+pub type VaultResult<T> = Result<T, VaultError>;
+
+/// Whether an I/O error means the vault cannot be written to at all.
+fn is_read_only_error(e: &std::io::Error) -> bool {
+    // EROFS on Unix, ERROR_WRITE_PROTECT on Windows.
+    #[cfg(unix)]
+    let read_only_code = 30;
+    #[cfg(windows)]
+    let read_only_code = 19;
+    #[cfg(not(any(unix, windows)))]
+    let read_only_code = -1;
+    e.raw_os_error() == Some(read_only_code)
+}
+
+/// `ErrorKind::NotADirectory` -> `not_a_directory`.
+fn kind_code(kind: ErrorKind) -> String {
+    let mut code = String::new();
+    for (idx, c) in format!("{kind:?}").chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if idx > 0 {
+                code.push('_');
+            }
+            code.push(c.to_ascii_lowercase());
+        } else {
+            code.push(c);
+        }
+    }
+    code
+}
+
+impl VaultError {
+    /// Classify an I/O error, prefixing its message with `context`.
+    pub fn io(e: std::io::Error, context: &str) -> Self {
+        if is_read_only_error(&e) {
+            return VaultError::PermissionDenied(format!("vault is read-only: {e}"));
+        }
+        let message = format!("{context}: {e}");
+        match e.kind() {
+            ErrorKind::NotFound => VaultError::NotFound(message),
+            ErrorKind::AlreadyExists => VaultError::AlreadyExists(message),
+            ErrorKind::PermissionDenied => VaultError::PermissionDenied(message),
+            kind => VaultError::Io { kind, message },
+        }
+    }
+
+    /// Internal failure that is not tied to a filesystem error.
+    pub fn other(message: impl Into<String>) -> Self {
+        VaultError::Io {
+            kind: ErrorKind::Other,
+            message: message.into(),
+        }
+    }
+
+    /// Stable machine-readable code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            VaultError::NotFound(_) => "not_found",
+            VaultError::AlreadyExists(_) => "already_exists",
+            VaultError::EscapesVault(_) => "escapes_vault",
+            VaultError::NotMarkdown(_) => "not_markdown",
+            VaultError::PermissionDenied(_) => "permission_denied",
+            VaultError::Conflict(_) => "conflict",
+            VaultError::Invalid(_) => "invalid",
+            VaultError::Io { .. } => "io",
+        }
+    }
+}
+
+impl std::fmt::Display for VaultError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VaultError::NotFound(message)
+            | VaultError::AlreadyExists(message)
+            | VaultError::EscapesVault(message)
+            | VaultError::NotMarkdown(message)
+            | VaultError::PermissionDenied(message)
+            | VaultError::Invalid(message)
+            | VaultError::Io { message, .. } => write!(f, "{message}"),
+            VaultError::Conflict(_) => write!(f, "note changed on disk"),
+        }
+    }
+}
+
+impl std::error::Error for VaultError {}
+
+impl Serialize for VaultError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("code", self.code())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            VaultError::Conflict(on_disk) => map.serialize_entry("on_disk", on_disk)?,
+            VaultError::Io { kind, .. } => map.serialize_entry("kind", &kind_code(*kind))?,
+            _ => {}
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VaultError;
+    use std::io::{Error, ErrorKind};
+
+    #[test]
+    fn classifies_io_errors() {
+        let missing = VaultError::io(Error::from(ErrorKind::NotFound), "failed to read note");
+        assert_eq!(missing.code(), "not_found");
+        assert!(missing.to_string().starts_with("failed to read note: "));
+
+        let other = VaultError::io(Error::from(ErrorKind::TimedOut), "failed to read note");
+        let json = serde_json::to_value(&other).unwrap();
+        assert_eq!(json["code"], "io");
+        assert_eq!(json["kind"], "timed_out");
+    }
+}
@@ -0,0 +1,338 @@
+use crate::error::{VaultError, VaultResult};
+use crate::vault::{read_note_impl, write_note_impl, NoteVersion};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Parsed frontmatter of a note.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoteMetadata {
+    /// All frontmatter keys, converted to JSON values
+    pub properties: Map<String, Value>,
+    /// Normalized `tags`, without a leading `#`
+    pub tags: Vec<String>,
+    /// Alternative names from `aliases` (or `alias`)
+    pub aliases: Vec<String>,
+    /// Problems found while parsing; the note is still usable
+    pub errors: Vec<String>,
+}
+
+/// Location of the frontmatter block within a note.
+#[derive(Debug, PartialEq)]
+struct Block {
+    /// Byte range of the YAML between the fences
+    yaml_start: usize,
+    yaml_end: usize,
+    /// Byte offset just past the closing fence line
+    end: usize,
+}
+
+fn is_fence(line: &str, closing: bool) -> bool {
+    let line = line.trim_end_matches(['\r', '\n']).trim_end();
+    line == "---" || (closing && line == "...")
+}
+
+/// Find a frontmatter block opened by `---` on the first line.
+fn find_block(text: &str) -> Option<Block> {
+    let start = if text.starts_with('\u{feff}') { 3 } else { 0 };
+    let mut lines = text[start..].split_inclusive('\n');
+    let first = lines.next()?;
+    if !first.ends_with('\n') || !is_fence(first, false) {
+        return None;
+    }
+
+    let yaml_start = start + first.len();
+    let mut offset = yaml_start;
+    for line in lines {
+        if is_fence(line, true) {
+            return Some(Block {
+                yaml_start,
+                yaml_end: offset,
+                end: offset + line.len(),
+            });
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Byte offset where the note body starts, after any frontmatter block.
+pub(crate) fn body_start(text: &str) -> usize {
+    find_block(text).map_or(0, |block| block.end)
+}
+
+fn yaml_key(key: &serde_yaml_ng::Value) -> String {
+    match key {
+        serde_yaml_ng::Value::String(s) => s.clone(),
+        other => serde_yaml_ng::to_string(other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn to_json(value: serde_yaml_ng::Value) -> Value {
+    match value {
+        serde_yaml_ng::Value::Null => Value::Null,
+        serde_yaml_ng::Value::Bool(b) => Value::Bool(b),
+        serde_yaml_ng::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                n.as_f64()
+                    .and_then(serde_json::Number::from_f64)
+                    .map_or(Value::Null, Value::Number)
+            }
+        }
+        serde_yaml_ng::Value::String(s) => Value::String(s),
+        serde_yaml_ng::Value::Sequence(seq) => Value::Array(seq.into_iter().map(to_json).collect()),
+        serde_yaml_ng::Value::Mapping(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (yaml_key(&k), to_json(v)))
+                .collect(),
+        ),
+        serde_yaml_ng::Value::Tagged(tagged) => to_json(tagged.value),
+    }
+}
+
+/// Read a string-or-list property such as `tags: [a, b]`. A single string is
+/// split on commas only when `split_commas` is set, as for `tags: a, b`;
+/// an alias like `"Smith, John"` stays whole.
+fn string_list(
+    properties: &Map<String, Value>,
+    key: &str,
+    split_commas: bool,
+    errors: &mut Vec<String>,
+) -> Vec<String> {
+    let items: Vec<String> = match properties.get(key) {
+        None | Some(Value::Null) => return Vec::new(),
+        Some(Value::String(s)) if split_commas => s.split(',').map(str::to_string).collect(),
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(values)) => {
+            let mut items = Vec::new();
+            for value in values {
+                match value {
+                    Value::String(s) => items.push(s.clone()),
+                    Value::Number(n) => items.push(n.to_string()),
+                    Value::Null => {}
+                    _ => errors.push(format!("`{key}` entries must be strings")),
+                }
+            }
+            items
+        }
+        Some(_) => {
+            errors.push(format!("`{key}` must be a string or a list of strings"));
+            return Vec::new();
+        }
+    };
+
+    let mut out: Vec<String> = Vec::new();
+    for item in items {
+        let item = item.trim();
+        if !item.is_empty() && !out.iter().any(|existing| existing == item) {
+            out.push(item.to_string());
+        }
+    }
+    out
+}
+
+/// Parse and validate the frontmatter of a note. Notes without frontmatter
+/// yield empty metadata.
+pub fn parse_metadata(text: &str) -> NoteMetadata {
+    let mut metadata = NoteMetadata::default();
+    let Some(block) = find_block(text) else {
+        return metadata;
+    };
+
+    let yaml = &text[block.yaml_start..block.yaml_end];
+    match serde_yaml_ng::from_str::<serde_yaml_ng::Value>(yaml) {
+        Ok(serde_yaml_ng::Value::Null) => {}
+        Ok(serde_yaml_ng::Value::Mapping(map)) => {
+            if let Value::Object(properties) = to_json(serde_yaml_ng::Value::Mapping(map)) {
+                metadata.properties = properties;
+            }
+        }
+        Ok(_) => metadata
+            .errors
+            .push("frontmatter must be a mapping of keys to values".to_string()),
+        Err(e) => metadata.errors.push(format!("invalid frontmatter: {e}")),
+    }
+
+    let mut errors = Vec::new();
+    metadata.tags = string_list(&metadata.properties, "tags", true, &mut errors)
+        .into_iter()
+        .map(|tag| tag.trim_start_matches('#').to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    metadata.aliases = string_list(&metadata.properties, "aliases", false, &mut errors);
+    if metadata.aliases.is_empty() {
+        metadata.aliases = string_list(&metadata.properties, "alias", false, &mut errors);
+    }
+    metadata.errors.extend(errors);
+    metadata
+}
+
+/// Whether a frontmatter line continues the value of the key above it.
+fn is_continuation(line: &str) -> bool {
+    line.starts_with([' ', '\t', '-']) && !is_fence(line, true)
+}
+
+/// Top-level key declared on a frontmatter line, if any.
+fn line_key(line: &str) -> Option<&str> {
+    if line.starts_with([' ', '\t', '-', '#']) {
+        return None;
+    }
+    let colon = line.find(':')?;
+    Some(line[..colon].trim().trim_matches(['"', '\'']))
+}
+
+/// Set (or with `None`, remove) one top-level frontmatter key, leaving every
+/// other line of the note untouched.
+pub(crate) fn set_property(text: &str, key: &str, value: Option<&Value>) -> VaultResult<String> {
+    if key.is_empty() || key.contains([':', '\n', '\r']) || key.trim() != key {
+        return Err(VaultError::Invalid(format!(
+            "invalid frontmatter key: {key:?}"
+        )));
+    }
+
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let entry = match value {
+        Some(value) => {
+            let mut map = Map::new();
+            map.insert(key.to_string(), value.clone());
+            let yaml = serde_yaml_ng::to_string(&map)
+                .map_err(|e| VaultError::Invalid(format!("invalid frontmatter value: {e}")))?;
+            yaml.replace('\n', newline)
+        }
+        None => String::new(),
+    };
+
+    let Some(block) = find_block(text) else {
+        if entry.is_empty() {
+            return Ok(text.to_string());
+        }
+        return Ok(format!("---{newline}{entry}---{newline}{text}"));
+    };
+    let yaml = &text[block.yaml_start..block.yaml_end];
+    // Editing line-wise could make a broken block worse; refuse instead.
+    if serde_yaml_ng::from_str::<serde_yaml_ng::Value>(yaml).is_err() {
+        return Err(VaultError::Invalid(
+            "frontmatter is not valid YAML".to_string(),
+        ));
+    }
+
+    let mut lines: Vec<&str> = yaml.split_inclusive('\n').collect();
+    let found = lines.iter().position(|line| line_key(line) == Some(key));
+    let mut updated = String::with_capacity(text.len() + entry.len());
+    updated.push_str(&text[..block.yaml_start]);
+    match found {
+        Some(idx) => {
+            let end = idx
+                + 1
+                + lines[idx + 1..]
+                    .iter()
+                    .take_while(|line| is_continuation(line))
+                    .count();
+            lines.splice(idx..end, [entry.as_str()]);
+            updated.extend(lines);
+        }
+        None => {
+            updated.push_str(yaml);
+            if !yaml.is_empty() && !yaml.ends_with('\n') {
+                updated.push_str(newline);
+            }
+            updated.push_str(&entry);
+        }
+    }
+    updated.push_str(&text[block.yaml_end..]);
+    Ok(updated)
+}
+
+pub fn get_note_metadata_impl(vault_path: &str, rel_path: &str) -> VaultResult<NoteMetadata> {
+    let note = read_note_impl(vault_path, rel_path)?;
+    Ok(parse_metadata(&note.contents))
+}
+
+/// Write one frontmatter key back to a note. With `expected`, the write is
+/// refused if the note changed on disk since that version.
+pub fn set_note_metadata_impl(
+    vault_path: &str,
+    rel_path: &str,
+    key: &str,
+    value: Option<Value>,
+    expected: Option<&NoteVersion>,
+) -> VaultResult<NoteVersion> {
+    let note = read_note_impl(vault_path, rel_path)?;
+    if let Some(expected) = expected {
+        if note.version.hash != expected.hash {
+            return Err(VaultError::Conflict(note));
+        }
+    }
+    let updated = set_property(&note.contents, key, value.as_ref())?;
+    write_note_impl(vault_path, rel_path, &updated, Some(&note.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{body_start, parse_metadata, set_note_metadata_impl, set_property};
+    use crate::testing::TempDir;
+    use serde_json::json;
+
+    const NOTE: &str = "---\ntitle: Hello\ntags:\n  - one\n  - \"#two\"\naliases: [JS, ECMAScript]\ndate: 2024-01-02\n---\n# Body\n";
+
+    #[test]
+    fn parses_frontmatter() {
+        let metadata = parse_metadata(NOTE);
+        assert!(metadata.errors.is_empty());
+        assert_eq!(metadata.tags, vec!["one", "two"]);
+        assert_eq!(metadata.aliases, vec!["JS", "ECMAScript"]);
+        assert_eq!(metadata.properties["date"], json!("2024-01-02"));
+        assert_eq!(&NOTE[body_start(NOTE)..], "# Body\n");
+
+        assert_eq!(
+            parse_metadata("# No frontmatter\n---\n"),
+            Default::default()
+        );
+        let scalars = parse_metadata("---\ntags: a, b\naliases: \"Smith, John\"\n---\n");
+        assert_eq!(scalars.tags, vec!["a", "b"]);
+        assert_eq!(scalars.aliases, vec!["Smith, John"]);
+        assert_eq!(parse_metadata("---\ntags: [a\n---\n").errors.len(), 1);
+        assert_eq!(parse_metadata("---\ntags: {a: 1}\n---\n").errors.len(), 1);
+    }
+
+    #[test]
+    fn sets_single_keys() {
+        let updated = set_property(NOTE, "tags", Some(&json!(["x"]))).unwrap();
+        assert_eq!(
+            updated,
+            "---\ntitle: Hello\ntags:\n- x\naliases: [JS, ECMAScript]\ndate: 2024-01-02\n---\n# Body\n"
+        );
+
+        let removed = set_property(NOTE, "title", None).unwrap();
+        assert!(removed.starts_with("---\ntags:\n"));
+
+        let added = set_property(NOTE, "status", Some(&json!("done"))).unwrap();
+        assert!(added.ends_with("date: 2024-01-02\nstatus: done\n---\n# Body\n"));
+
+        let created = set_property("Body\r\n", "status", Some(&json!("draft"))).unwrap();
+        assert_eq!(created, "---\r\nstatus: draft\r\n---\r\nBody\r\n");
+
+        assert!(set_property(NOTE, "bad: key", None).is_err());
+    }
+
+    #[test]
+    fn keeps_body_line_endings() {
+        let dir = TempDir::new("frontmatter-line-endings");
+        let vault = dir.to_string_lossy().to_string();
+        std::fs::write(
+            dir.join("Note.md"),
+            "---\r\ntitle: a\r\n---\r\none\ntwo\r\n",
+        )
+        .unwrap();
+
+        set_note_metadata_impl(&vault, "Note.md", "status", Some(json!("done")), None).unwrap();
+        let written = std::fs::read_to_string(dir.join("Note.md")).unwrap();
+        assert!(written.ends_with("---\r\none\ntwo\r\n"), "{written:?}");
+        assert!(written.contains("status: done"));
+    }
+}
@@ -1,18 +1,45 @@
+use crate::error::VaultResult;
+use crate::index::{rel_path_key, LinkIndex, LinkIndexStore};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsStr;
-use std::path::{Component, Path};
-use std::time::UNIX_EPOCH;
+use std::path::Path;
 
-/// A node in the graph representing a note.
+/// What a graph node stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GraphNodeKind {
+    Note,
+    Tag,
+    /// A link target no note exists for yet
+    Unresolved,
+}
+
+/// What a graph edge stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GraphEdgeKind {
+    /// A wikilink from one note to another
+    Link,
+    /// A note carrying a tag
+    Tag,
+}
+
+/// Prefixes of tag and unresolved node ids, keeping them apart from note ids.
+const TAG_ID_PREFIX: &str = "tag:";
+const UNRESOLVED_ID_PREFIX: &str = "unresolved:";
+
+/// A node in the graph representing a note or a tag.
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GraphNode {
-    /// Stable identifier - normalized relative path without .md
+    /// Stable identifier - normalized relative path without .md, or
+    /// `tag:<tag>` / `unresolved:<target>` for the other node kinds
     pub id: String,
+    pub kind: GraphNodeKind,
     /// Display name (filename without extension)
     pub title: String,
-    /// Relative path including folders
+    /// Relative path including folders (empty for tag and unresolved nodes)
     pub rel_path: String,
     /// Whether this file matches ignore rules
     pub is_hidden: bool,
@@ -34,6 +61,7 @@ pub struct GraphEdge {
     pub source_id: String,
     /// Target node id (will match a node if resolved)
     pub target_id: String,
+    pub kind: GraphEdgeKind,
     /// Number of times source links to target
     pub count: u32,
 }
@@ -50,6 +78,12 @@ pub struct GraphData {
 #[serde(rename_all = "camelCase")]
 pub struct GraphOptions {
     pub show_hidden: bool,
+    /// Emit tag nodes and note→tag edges
+    #[serde(default)]
+    pub include_tags: bool,
+    /// Emit nodes for link targets that do not exist yet
+    #[serde(default)]
+    pub include_unresolved: bool,
 }
 
 /// Check if a path segment represents a hidden/ignored item.
@@ -67,7 +101,7 @@ fn is_hidden_segment(segment: &str) -> bool {
 }
 
 /// Check if a relative path is hidden based on any segment.
-fn is_hidden_path(rel_path: &str) -> bool {
+pub(crate) fn is_hidden_path(rel_path: &str) -> bool {
     for segment in rel_path.split('/') {
         if segment.is_empty() {
             continue;
@@ -79,147 +113,30 @@ fn is_hidden_path(rel_path: &str) -> bool {
     false
 }
 
-/// Check if a path is a Markdown file.
-fn is_markdown_file(path: &Path) -> bool {
-    match path.extension().and_then(OsStr::to_str) {
-        Some(ext) => {
-            let ext = ext.to_ascii_lowercase();
-            ext == "md" || ext == "markdown"
-        }
-        None => false,
-    }
-}
-
-/// Convert a path to a relative string.
-fn path_to_rel_string(vault: &Path, path: &Path) -> Result<String, String> {
-    let rel = path
-        .strip_prefix(vault)
-        .map_err(|_| "path escapes vault".to_string())?;
-
-    let mut parts: Vec<String> = Vec::new();
-    for component in rel.components() {
-        match component {
-            Component::Normal(p) => parts.push(p.to_string_lossy().to_string()),
-            Component::CurDir => {}
-            _ => return Err("unsupported path component".to_string()),
-        }
-    }
-    Ok(parts.join("/"))
-}
-
-/// Get the display name (stem) from a path.
-fn display_name_for_path(path: &Path) -> String {
-    path.file_stem()
+/// Get the display name (stem) from a rel_path.
+fn display_name_for_path(rel_path: &str) -> String {
+    Path::new(rel_path)
+        .file_stem()
         .and_then(OsStr::to_str)
         .unwrap_or("(unknown)")
         .to_string()
 }
 
-/// Normalize a wikilink target for matching.
-/// Rules: trim outer spaces, strip alias after |, case-insensitive, drop .md
-fn normalize_wikilink_target(target: &str) -> String {
-    let raw = target.split('|').next().unwrap_or("");
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return String::new();
-    }
-
-    let mut s = trimmed.to_string();
-    if s.to_ascii_lowercase().ends_with(".md") {
-        s.truncate(s.len().saturating_sub(3));
-    }
-
-    s.to_ascii_lowercase()
-}
-
-/// Extract wikilinks from note text.
-fn extract_wikilinks(text: &str) -> Vec<String> {
-    let mut links = Vec::new();
-    let mut seen = HashSet::new();
-    let mut idx = 0;
-
-    while let Some(start) = text[idx..].find("[[") {
-        let start = idx + start + 2;
-        if let Some(end) = text[start..].find("]]") {
-            let end = start + end;
-            let raw = &text[start..end];
-            let normalized = normalize_wikilink_target(raw);
-            if !normalized.is_empty() && seen.insert(normalized.clone()) {
-                links.push(normalized);
-            }
-            idx = end + 2;
-        } else {
-            break;
-        }
-    }
-    links
-}
-
-/// Collect Markdown files recursively from a directory.
-fn collect_markdown_files(
-    vault: &Path,
-    dir: &Path,
-    entries: &mut Vec<(String, std::path::PathBuf)>,
-) -> Result<(), String> {
-    let read_dir = std::fs::read_dir(dir).map_err(|e| format!("failed to read directory: {e}"))?;
-
-    for entry in read_dir {
-        let entry = entry.map_err(|e| format!("failed to read entry: {e}"))?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            collect_markdown_files(vault, &path, entries)?;
-            continue;
-        }
-
-        if path.is_file() && is_markdown_file(&path) {
-            let rel_path = path_to_rel_string(vault, &path)?;
-            entries.push((rel_path, path));
-        }
-    }
-
-    Ok(())
-}
-
 /// Build the graph from a vault.
-pub fn build_graph_impl(vault_path: &str, options: GraphOptions) -> Result<GraphData, String> {
-    let vault =
-        std::fs::canonicalize(vault_path).map_err(|e| format!("invalid vault path: {e}"))?;
-    if !vault.is_dir() {
-        return Err("vault path is not a directory".to_string());
-    }
-
-    // Collect all markdown files
-    let mut file_entries: Vec<(String, std::path::PathBuf)> = Vec::new();
-    collect_markdown_files(&vault, &vault, &mut file_entries)?;
-
-    // Build mapping from normalized filename stem to rel_path for resolution
-    // Key: lowercase filename without extension, Value: rel_path
-    let mut stem_to_rel_path: HashMap<String, String> = HashMap::new();
-    for (rel_path, _) in &file_entries {
-        let stem = rel_path
-            .rsplit('/')
-            .next()
-            .unwrap_or(rel_path)
-            .trim_end_matches(".md")
-            .trim_end_matches(".markdown")
-            .to_ascii_lowercase();
-        // First match wins for duplicate stems
-        stem_to_rel_path
-            .entry(stem)
-            .or_insert_with(|| rel_path.clone());
-    }
+pub fn build_graph_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    options: GraphOptions,
+) -> VaultResult<GraphData> {
+    store.with_index(vault_path, |_, index| {
+        build_graph_from_index(index, &options)
+    })
+}
 
-    // Also map full rel_path (without extension) for path-based links
-    for (rel_path, _) in &file_entries {
-        let without_ext = rel_path
-            .trim_end_matches(".md")
-            .trim_end_matches(".markdown")
-            .to_ascii_lowercase();
-        stem_to_rel_path
-            .entry(without_ext)
-            .or_insert_with(|| rel_path.clone());
-    }
+/// Build the graph from an up-to-date link index.
+fn build_graph_from_index(index: &LinkIndex, options: &GraphOptions) -> GraphData {
+    // Resolves link targets by path, folder proximity and aliases
+    let resolver = index.resolver();
 
     // Track in-degree for each node
     let mut in_degree: HashMap<String, u32> = HashMap::new();
@@ -228,8 +145,8 @@ pub fn build_graph_impl(vault_path: &str, options: GraphOptions) -> Result<Graph
     // Edges: (source_rel_path, target_rel_path) -> count
     let mut edge_counts: HashMap<(String, String), u32> = HashMap::new();
 
-    // Process each file to extract links
-    for (rel_path, path) in &file_entries {
+    // Process each note's indexed links
+    for (rel_path, note) in index.notes() {
         let is_hidden = is_hidden_path(rel_path);
 
         // Skip hidden files if not showing hidden
@@ -237,17 +154,11 @@ pub fn build_graph_impl(vault_path: &str, options: GraphOptions) -> Result<Graph
             continue;
         }
 
-        let content = match std::fs::read_to_string(path) {
-            Ok(c) => c,
-            Err(_) => continue,
-        };
-
-        let links = extract_wikilinks(&content);
-        out_degree.insert(rel_path.clone(), links.len() as u32);
+        out_degree.insert(rel_path.clone(), note.links.len() as u32);
 
-        for link in links {
+        for link in &note.links {
             // Try to resolve the link to an existing file
-            if let Some(target_rel_path) = stem_to_rel_path.get(&link) {
+            if let Some(target_rel_path) = resolver.resolve(rel_path, link) {
                 let target_hidden = is_hidden_path(target_rel_path);
 
                 // Skip edges to hidden targets if not showing hidden
@@ -256,10 +167,10 @@ pub fn build_graph_impl(vault_path: &str, options: GraphOptions) -> Result<Graph
                 }
 
                 // Increment in-degree
-                *in_degree.entry(target_rel_path.clone()).or_insert(0) += 1;
+                *in_degree.entry(target_rel_path.to_string()).or_insert(0) += 1;
 
                 // Track edge
-                let key = (rel_path.clone(), target_rel_path.clone());
+                let key = (rel_path.clone(), target_rel_path.to_string());
                 *edge_counts.entry(key).or_insert(0) += 1;
             }
         }
@@ -267,7 +178,7 @@ pub fn build_graph_impl(vault_path: &str, options: GraphOptions) -> Result<Graph
 
     // Build nodes
     let mut nodes: Vec<GraphNode> = Vec::new();
-    for (rel_path, path) in &file_entries {
+    for (rel_path, note) in index.notes() {
         let is_hidden = is_hidden_path(rel_path);
 
         // Skip hidden files unless showing hidden
@@ -275,101 +186,122 @@ pub fn build_graph_impl(vault_path: &str, options: GraphOptions) -> Result<Graph
             continue;
         }
 
-        let title = display_name_for_path(path);
-        let id = rel_path
-            .trim_end_matches(".md")
-            .trim_end_matches(".markdown")
-            .to_ascii_lowercase();
-
-        // Get file metadata for timestamps
-        let (created_at, modified_at) = match std::fs::metadata(path) {
-            Ok(meta) => {
-                let modified = meta
-                    .modified()
-                    .ok()
-                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-                    .map(|d| d.as_millis() as u64);
-
-                // macOS supports created time, other platforms may not
-                #[cfg(target_os = "macos")]
-                let created = meta
-                    .created()
-                    .ok()
-                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-                    .map(|d| d.as_millis() as u64);
-
-                #[cfg(not(target_os = "macos"))]
-                let created = None;
-
-                (created, modified)
-            }
-            Err(_) => (None, None),
-        };
-
         nodes.push(GraphNode {
-            id,
-            title,
+            id: rel_path_key(rel_path),
+            kind: GraphNodeKind::Note,
+            title: display_name_for_path(rel_path),
             rel_path: rel_path.clone(),
             is_hidden,
             degree_in: *in_degree.get(rel_path).unwrap_or(&0),
             degree_out: *out_degree.get(rel_path).unwrap_or(&0),
-            created_at,
-            modified_at,
+            created_at: note.created_ms,
+            modified_at: Some(note.mtime_ms),
         });
     }
 
     // Build edges
-    let edges: Vec<GraphEdge> = edge_counts
+    let mut edges: Vec<GraphEdge> = edge_counts
         .into_iter()
-        .map(|((source_rel_path, target_rel_path), count)| {
-            let source_id = source_rel_path
-                .trim_end_matches(".md")
-                .trim_end_matches(".markdown")
-                .to_ascii_lowercase();
-            let target_id = target_rel_path
-                .trim_end_matches(".md")
-                .trim_end_matches(".markdown")
-                .to_ascii_lowercase();
-            GraphEdge {
-                source_id,
-                target_id,
-                count,
-            }
+        .map(|((source_rel_path, target_rel_path), count)| GraphEdge {
+            source_id: rel_path_key(&source_rel_path),
+            target_id: rel_path_key(&target_rel_path),
+            kind: GraphEdgeKind::Link,
+            count,
         })
         .collect();
 
+    if options.include_tags {
+        add_tag_nodes(index, options, &mut nodes, &mut edges);
+    }
+    if options.include_unresolved {
+        add_unresolved_nodes(index, options, &mut nodes, &mut edges);
+    }
+
     // Sort nodes by title for consistent ordering
     let mut sorted_nodes = nodes;
-    sorted_nodes.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()));
+    sorted_nodes.sort_by_key(|node| node.title.to_lowercase());
 
-    Ok(GraphData {
+    GraphData {
         nodes: sorted_nodes,
         edges,
-    })
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{extract_wikilinks, is_hidden_path, normalize_wikilink_target};
-
-    #[test]
-    fn normalize_targets() {
-        assert_eq!(normalize_wikilink_target("  Note Name  "), "note name");
-        assert_eq!(normalize_wikilink_target(" Note | Alias "), "note");
-        assert_eq!(normalize_wikilink_target("Foo.md"), "foo");
-        assert_eq!(normalize_wikilink_target("Foo.MD"), "foo");
-        assert_eq!(normalize_wikilink_target(""), "");
-        assert_eq!(normalize_wikilink_target("folder/Note"), "folder/note");
+/// Add a node per tag used by a visible note, plus an edge from every note
+/// to each of its tags.
+fn add_tag_nodes(
+    index: &LinkIndex,
+    options: &GraphOptions,
+    nodes: &mut Vec<GraphNode>,
+    edges: &mut Vec<GraphEdge>,
+) {
+    let mut tag_degree: BTreeMap<&str, u32> = BTreeMap::new();
+    for (rel_path, note) in index.notes() {
+        if is_hidden_path(rel_path) && !options.show_hidden {
+            continue;
+        }
+        for tag in &note.tags {
+            *tag_degree.entry(tag.as_str()).or_insert(0) += 1;
+            edges.push(GraphEdge {
+                source_id: rel_path_key(rel_path),
+                target_id: format!("{TAG_ID_PREFIX}{tag}"),
+                kind: GraphEdgeKind::Tag,
+                count: 1,
+            });
+        }
     }
 
-    #[test]
-    fn extract_links() {
-        let links = extract_wikilinks("[[Foo]] [[ foo ]] [[FOO|bar]]");
-        assert_eq!(links, vec!["foo".to_string()]);
+    nodes.extend(tag_degree.into_iter().map(|(tag, degree)| GraphNode {
+        id: format!("{TAG_ID_PREFIX}{tag}"),
+        kind: GraphNodeKind::Tag,
+        title: format!("#{tag}"),
+        rel_path: String::new(),
+        is_hidden: false,
+        degree_in: degree,
+        degree_out: 0,
+        created_at: None,
+        modified_at: None,
+    }));
+}
 
-        let links2 = extract_wikilinks("See [[Note A]] and [[Note B]].");
-        assert_eq!(links2, vec!["note a".to_string(), "note b".to_string()]);
+/// Add a node per unresolved link target, linked from every note
+/// referencing it. The node title is the target as it would be created.
+fn add_unresolved_nodes(
+    index: &LinkIndex,
+    options: &GraphOptions,
+    nodes: &mut Vec<GraphNode>,
+    edges: &mut Vec<GraphEdge>,
+) {
+    let unresolved =
+        index.unresolved_links(|rel_path| options.show_hidden || !is_hidden_path(rel_path));
+    for link in unresolved {
+        let id = format!("{UNRESOLVED_ID_PREFIX}{}", link.target);
+        edges.extend(link.sources.iter().map(|source| GraphEdge {
+            source_id: rel_path_key(source),
+            target_id: id.clone(),
+            kind: GraphEdgeKind::Link,
+            count: 1,
+        }));
+        nodes.push(GraphNode {
+            id,
+            kind: GraphNodeKind::Unresolved,
+            // Markdown link targets carry a leading `/`; drop it for display.
+            title: link.target.trim_start_matches('/').to_string(),
+            rel_path: String::new(),
+            is_hidden: false,
+            degree_in: link.count as u32,
+            degree_out: 0,
+            created_at: None,
+            modified_at: None,
+        });
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_graph_impl, is_hidden_path, GraphEdgeKind, GraphNodeKind, GraphOptions};
+    use crate::index::LinkIndexStore;
+    use crate::testing::TempDir;
 
     #[test]
     fn hidden_paths() {
@@ -379,4 +311,37 @@ mod tests {
         assert!(!is_hidden_path("regular/folder/note.md"));
         assert!(!is_hidden_path("notes/my-note.md"));
     }
+
+    #[test]
+    fn tag_nodes() {
+        let dir = TempDir::new("graph-tags");
+        std::fs::write(dir.join("A.md"), "---\ntags: [area]\n---\n[[B]] #todo\n").unwrap();
+        std::fs::write(dir.join("B.md"), "#todo\n").unwrap();
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+
+        let options = |include_tags| GraphOptions {
+            show_hidden: false,
+            include_tags,
+            include_unresolved: false,
+        };
+        let plain = build_graph_impl(&store, &vault, options(false)).unwrap();
+        assert!(plain.nodes.iter().all(|n| n.kind == GraphNodeKind::Note));
+        assert_eq!(plain.edges.len(), 1);
+
+        let graph = build_graph_impl(&store, &vault, options(true)).unwrap();
+        let tags: Vec<(&str, u32)> = graph
+            .nodes
+            .iter()
+            .filter(|n| n.kind == GraphNodeKind::Tag)
+            .map(|n| (n.id.as_str(), n.degree_in))
+            .collect();
+        assert_eq!(tags, vec![("tag:area", 1), ("tag:todo", 2)]);
+        let tag_edges = graph
+            .edges
+            .iter()
+            .filter(|e| e.kind == GraphEdgeKind::Tag)
+            .count();
+        assert_eq!(tag_edges, 3);
+    }
 }
@@ -0,0 +1,709 @@
+use crate::error::{VaultError, VaultResult};
+use crate::frontmatter::{parse_metadata, NoteMetadata};
+use crate::graph::is_hidden_path;
+use crate::mentions::note_words;
+use crate::resolver::Resolver;
+use crate::tags::note_tags;
+use crate::vault::{
+    content_hash, display_name_for_path, list_markdown_files_impl, resolve_vault,
+    sort_note_entries, write_atomic, NoteEntry,
+};
+use crate::walk::notes_at;
+use crate::watcher::VaultChanges;
+use crate::wikilinks::{extract_links, is_attachment_target, normalize_wikilink_target};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bump when the on-disk layout or link extraction rules change so stale
+/// indexes are rebuilt instead of reused.
+const INDEX_VERSION: u32 = 6;
+
+/// Cached link data for a single note.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedNote {
+    /// File modification timestamp in milliseconds
+    pub mtime_ms: u64,
+    /// File size in bytes
+    pub size: u64,
+    /// File creation timestamp in milliseconds (best effort)
+    pub created_ms: Option<u64>,
+    /// Normalized wikilink targets and `/`-prefixed vault paths of Markdown
+    /// links, deduplicated in document order
+    pub links: Vec<String>,
+    /// External URLs from Markdown links
+    #[serde(default)]
+    pub external_links: Vec<String>,
+    /// Parsed frontmatter
+    #[serde(default)]
+    pub metadata: NoteMetadata,
+    /// Normalized inline and frontmatter tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Lowercased words of the note, sorted, used to find notes that may
+    /// mention another without reading every file
+    #[serde(default)]
+    pub words: Vec<String>,
+}
+
+/// Link index for one vault, keyed by note rel_path.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkIndex {
+    version: u32,
+    notes: BTreeMap<String, IndexedNote>,
+}
+
+/// Resolved outgoing link of a note.
+#[derive(Debug, Serialize)]
+pub struct OutgoingLink {
+    /// Normalized wikilink target as written in the note, or the URL of an
+    /// external link
+    pub target: String,
+    /// Note the target resolves to, if it exists
+    pub rel_path: Option<String>,
+    /// Whether the target is a URL outside the vault
+    pub external: bool,
+}
+
+/// An alias that does not resolve to (only) the note declaring it.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AliasCollision {
+    /// Normalized alias
+    pub alias: String,
+    /// Notes declaring the alias in their frontmatter
+    pub declared_by: Vec<String>,
+    /// Note that `[[alias]]` actually resolves to
+    pub resolved_to: String,
+}
+
+/// A link target no note resolves to.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct UnresolvedLink {
+    /// Normalized wikilink target
+    pub target: String,
+    /// Notes linking to the target, in rel_path order
+    pub sources: Vec<String>,
+    /// Number of notes linking to the target
+    pub count: usize,
+}
+
+fn to_millis(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+/// Read the (mtime, size, created) stamp used to detect changed notes.
+pub(crate) fn file_stamp(path: &Path) -> Option<(u64, u64, Option<u64>)> {
+    let meta = std::fs::metadata(path).ok()?;
+    let mtime_ms = meta.modified().ok().and_then(to_millis)?;
+
+    // macOS supports created time, other platforms may not
+    #[cfg(target_os = "macos")]
+    let created_ms = meta.created().ok().and_then(to_millis);
+
+    #[cfg(not(target_os = "macos"))]
+    let created_ms = None;
+
+    Some((mtime_ms, meta.len(), created_ms))
+}
+
+/// Strip the Markdown extension and lowercase a rel_path for link matching.
+pub(crate) fn rel_path_key(rel_path: &str) -> String {
+    rel_path
+        .trim_end_matches(".md")
+        .trim_end_matches(".markdown")
+        .to_ascii_lowercase()
+}
+
+impl LinkIndex {
+    fn new() -> Self {
+        LinkIndex {
+            version: INDEX_VERSION,
+            notes: BTreeMap::new(),
+        }
+    }
+
+    /// Indexed data of a single note.
+    pub fn note(&self, rel_path: &str) -> Option<&IndexedNote> {
+        self.notes.get(rel_path)
+    }
+
+    /// Iterate indexed notes in rel_path order.
+    pub fn notes(&self) -> impl Iterator<Item = (&String, &IndexedNote)> {
+        self.notes.iter()
+    }
+
+    /// Bring the index in line with the vault on disk, re-reading only notes
+    /// whose mtime or size changed. Returns whether anything changed.
+    pub fn refresh(&mut self, vault: &Path) -> VaultResult<bool> {
+        let files = list_markdown_files_impl(&vault.to_string_lossy())?;
+        let mut changed = false;
+
+        let mut seen: HashSet<&str> = HashSet::with_capacity(files.len());
+        for file in &files {
+            seen.insert(file.rel_path.as_str());
+            changed |= self.update_note(vault, &file.rel_path);
+        }
+
+        let before = self.notes.len();
+        self.notes
+            .retain(|rel_path, _| seen.contains(rel_path.as_str()));
+        changed |= self.notes.len() != before;
+
+        Ok(changed)
+    }
+
+    /// Re-index a single note if it changed on disk, or drop it if it no
+    /// longer exists. Returns whether the index changed.
+    pub fn update_note(&mut self, vault: &Path, rel_path: &str) -> bool {
+        let path = vault.join(rel_path);
+        let Some((mtime_ms, size, created_ms)) = file_stamp(&path) else {
+            return self.notes.remove(rel_path).is_some();
+        };
+
+        if let Some(existing) = self.notes.get(rel_path) {
+            if existing.mtime_ms == mtime_ms && existing.size == size {
+                return false;
+            }
+        }
+
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return self.notes.remove(rel_path).is_some();
+        };
+        let metadata = parse_metadata(&text);
+        let links = extract_links(rel_path, &text);
+
+        self.notes.insert(
+            rel_path.to_string(),
+            IndexedNote {
+                mtime_ms,
+                size,
+                created_ms,
+                links: links.notes,
+                external_links: links.external,
+                tags: note_tags(&text, &metadata.tags),
+                words: note_words(&text),
+                metadata,
+            },
+        );
+        true
+    }
+
+    /// Re-read the notes now at or below each of `rel_paths`, dropping those
+    /// that are gone. Unlike `update_note`, notes are re-read even when their
+    /// size and mtime look unchanged. Returns whether the index changed.
+    pub fn replace_notes(&mut self, vault: &Path, rel_paths: &[String]) -> bool {
+        let mut changed = false;
+        for rel_path in rel_paths {
+            let prefix = format!("{rel_path}/");
+            let nested: Vec<String> = self
+                .notes
+                .range(prefix.clone()..)
+                .map(|(note, _)| note)
+                .take_while(|note| note.starts_with(&prefix))
+                .cloned()
+                .collect();
+            for note in nested {
+                changed |= self.notes.remove(&note).is_some();
+            }
+            changed |= self.notes.remove(rel_path).is_some();
+            for note in notes_at(vault, rel_path) {
+                changed |= self.update_note(vault, &note);
+            }
+        }
+        changed
+    }
+
+    /// Resolver for the links of every indexed note.
+    pub fn resolver(&self) -> Resolver {
+        Resolver::new(self.notes.keys(), self.aliases())
+    }
+
+    /// Resolver for the vault as it would look with every note moved to
+    /// `moved(rel_path)`.
+    pub fn moved_resolver(&self, moved: impl Fn(&str) -> String) -> Resolver {
+        let rel_paths: Vec<String> = self.notes.keys().map(|p| moved(p)).collect();
+        let aliases: Vec<(String, String)> =
+            self.aliases().map(|(p, alias)| (moved(p), alias)).collect();
+        Resolver::new(
+            rel_paths.iter(),
+            aliases.iter().map(|(p, alias)| (p, alias.clone())),
+        )
+    }
+
+    /// Normalized frontmatter aliases with the note declaring them.
+    fn aliases(&self) -> impl Iterator<Item = (&String, String)> {
+        self.notes.iter().flat_map(|(rel_path, note)| {
+            note.metadata
+                .aliases
+                .iter()
+                .map(|alias| normalize_wikilink_target(alias))
+                .filter(|alias| !alias.is_empty())
+                .map(move |alias| (rel_path, alias))
+        })
+    }
+
+    /// Aliases declared by several notes, or shadowed by a note name, as
+    /// resolved from the vault root.
+    pub fn alias_collisions(&self) -> Vec<AliasCollision> {
+        let mut declared: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (rel_path, alias) in self.aliases() {
+            let owners = declared.entry(alias).or_default();
+            if !owners.contains(rel_path) {
+                owners.push(rel_path.clone());
+            }
+        }
+
+        let resolver = self.resolver();
+        declared
+            .into_iter()
+            .filter_map(|(alias, declared_by)| {
+                let resolved_to = resolver.resolve("", &alias)?.to_string();
+                (declared_by.len() > 1 || declared_by[0] != resolved_to).then_some(AliasCollision {
+                    alias,
+                    declared_by,
+                    resolved_to,
+                })
+            })
+            .collect()
+    }
+
+    /// Link targets that do not resolve to any note, most referenced first.
+    /// Only links from notes accepted by `include_source` are considered;
+    /// links to attachments such as `![[photo.png]]` never count.
+    pub fn unresolved_links(&self, include_source: impl Fn(&str) -> bool) -> Vec<UnresolvedLink> {
+        let resolver = self.resolver();
+        let mut sources: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for (rel_path, note) in &self.notes {
+            if !include_source(rel_path) {
+                continue;
+            }
+            for link in &note.links {
+                if !is_attachment_target(link) && resolver.resolve(rel_path, link).is_none() {
+                    sources.entry(link).or_default().push(rel_path.clone());
+                }
+            }
+        }
+
+        let mut unresolved: Vec<UnresolvedLink> = sources
+            .into_iter()
+            .map(|(target, sources)| UnresolvedLink {
+                target: target.to_string(),
+                count: sources.len(),
+                sources,
+            })
+            .collect();
+        unresolved.sort_by_key(|link| std::cmp::Reverse(link.count));
+        unresolved
+    }
+
+    /// Notes linking to the given normalized target, in rel_path order.
+    pub fn backlinks(&self, target: &str) -> Vec<String> {
+        self.notes
+            .iter()
+            .filter(|(_, note)| note.links.iter().any(|l| l == target))
+            .map(|(rel_path, _)| rel_path.clone())
+            .collect()
+    }
+
+    /// Notes whose links resolve to `rel_path`, by name, path or alias.
+    pub fn resolved_backlinks(&self, rel_path: &str) -> Vec<String> {
+        let resolver = self.resolver();
+        self.notes
+            .iter()
+            .filter(|(source, note)| {
+                note.links
+                    .iter()
+                    .any(|link| resolver.resolve(source, link) == Some(rel_path))
+            })
+            .map(|(source, _)| source.clone())
+            .collect()
+    }
+
+    /// Notes outside `rel_paths` whose links resolve to any note in it.
+    pub fn linked_from(&self, rel_paths: &HashSet<&str>) -> Vec<String> {
+        let resolver = self.resolver();
+        self.notes
+            .iter()
+            .filter(|(rel_path, _)| !rel_paths.contains(rel_path.as_str()))
+            .filter(|(source, note)| {
+                note.links.iter().any(|link| {
+                    resolver
+                        .resolve(source, link)
+                        .is_some_and(|target| rel_paths.contains(target))
+                })
+            })
+            .map(|(rel_path, _)| rel_path.clone())
+            .collect()
+    }
+
+    /// Outgoing links of a note, resolved against the rest of the vault,
+    /// followed by its external links.
+    pub fn outgoing_links(&self, rel_path: &str) -> Option<Vec<OutgoingLink>> {
+        let note = self.notes.get(rel_path)?;
+        let resolver = self.resolver();
+        let notes = note.links.iter().map(|target| OutgoingLink {
+            target: target.clone(),
+            rel_path: resolver.resolve(rel_path, target).map(str::to_string),
+            external: false,
+        });
+        let external = note.external_links.iter().map(|url| OutgoingLink {
+            target: url.clone(),
+            rel_path: None,
+            external: true,
+        });
+        Some(notes.chain(external).collect())
+    }
+}
+
+/// Shared, persisted link indexes for every vault opened in this session.
+pub struct LinkIndexStore {
+    dir: Option<PathBuf>,
+    indexes: Mutex<HashMap<PathBuf, LinkIndex>>,
+}
+
+impl LinkIndexStore {
+    /// Create a store persisting indexes under `dir`. Without a directory the
+    /// index lives in memory only and is rebuilt on every launch.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        LinkIndexStore {
+            dir,
+            indexes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn index_file(&self, vault: &Path) -> Option<PathBuf> {
+        let key = content_hash(vault.to_string_lossy().as_bytes());
+        self.dir.as_ref().map(|dir| dir.join(format!("{key}.json")))
+    }
+
+    fn load(&self, vault: &Path) -> LinkIndex {
+        let Some(path) = self.index_file(vault) else {
+            return LinkIndex::new();
+        };
+        let Ok(raw) = std::fs::read_to_string(&path) else {
+            return LinkIndex::new();
+        };
+        match serde_json::from_str::<LinkIndex>(&raw) {
+            Ok(index) if index.version == INDEX_VERSION => index,
+            _ => LinkIndex::new(),
+        }
+    }
+
+    fn save(&self, vault: &Path, index: &LinkIndex) -> VaultResult<()> {
+        let Some(path) = self.index_file(vault) else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| VaultError::io(e, "failed to create index folder"))?;
+        }
+        let json = serde_json::to_string(index)
+            .map_err(|e| VaultError::other(format!("failed to encode index: {e}")))?;
+        write_atomic(&path, json.as_bytes())
+    }
+
+    /// Run `f` against the index for the vault. On first use the persisted
+    /// index is loaded and refreshed against the disk; afterwards the watcher
+    /// and `notes_changed` keep it current, so queries never walk the vault.
+    pub fn with_index<T>(
+        &self,
+        vault_path: &str,
+        f: impl FnOnce(&Path, &LinkIndex) -> T,
+    ) -> VaultResult<T> {
+        let vault = resolve_vault(vault_path)?;
+
+        let mut indexes = self
+            .indexes
+            .lock()
+            .map_err(|_| VaultError::other("link index is unavailable"))?;
+        let index = match indexes.entry(vault.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                // Notes may have changed while the app was closed.
+                let mut index = self.load(&vault);
+                if index.refresh(&vault)? {
+                    if let Err(e) = self.save(&vault, &index) {
+                        log::warn!("{e}");
+                    }
+                }
+                entry.insert(index)
+            }
+        };
+
+        Ok(f(&vault, index))
+    }
+
+    /// Bring an already loaded index in line with the whole vault, for when
+    /// changes may have been missed, e.g. while the vault was not watched.
+    pub fn refresh(&self, vault_path: &str) {
+        let Ok(vault) = resolve_vault(vault_path) else {
+            return;
+        };
+        let Ok(mut indexes) = self.indexes.lock() else {
+            return;
+        };
+        let Some(index) = indexes.get_mut(&vault) else {
+            return;
+        };
+        match index.refresh(&vault) {
+            Ok(true) => {
+                if let Err(e) = self.save(&vault, index) {
+                    log::warn!("{e}");
+                }
+            }
+            Ok(false) => {}
+            Err(e) => log::warn!("{e}"),
+        }
+    }
+
+    /// Re-index notes the app just created, wrote, moved or deleted, given by
+    /// their rel_paths or those of their folders.
+    pub fn notes_changed(&self, vault_path: &str, rel_paths: &[String]) {
+        let Ok(vault) = resolve_vault(vault_path) else {
+            return;
+        };
+        let Ok(mut indexes) = self.indexes.lock() else {
+            return;
+        };
+        let Some(index) = indexes.get_mut(&vault) else {
+            return;
+        };
+        if index.replace_notes(&vault, rel_paths) {
+            if let Err(e) = self.save(&vault, index) {
+                log::warn!("{e}");
+            }
+        }
+    }
+
+    /// Apply a batch of watcher changes to an already loaded index so the
+    /// next query does not have to re-read the affected notes.
+    pub fn apply_changes(&self, vault: &Path, changes: &VaultChanges) {
+        let Ok(mut indexes) = self.indexes.lock() else {
+            return;
+        };
+        let Some(index) = indexes.get_mut(vault) else {
+            return;
+        };
+
+        let mut changed = false;
+        if changes.rescan {
+            match index.refresh(vault) {
+                Ok(refreshed) => changed |= refreshed,
+                Err(e) => log::warn!("{e}"),
+            }
+        } else {
+            let touched = changes
+                .created
+                .iter()
+                .chain(&changes.modified)
+                .chain(&changes.deleted)
+                .chain(changes.renamed.iter().flat_map(|r| [&r.from, &r.to]));
+            for rel_path in touched {
+                changed |= index.update_note(vault, rel_path);
+            }
+        }
+
+        if changed {
+            if let Err(e) = self.save(vault, index) {
+                log::warn!("{e}");
+            }
+        }
+    }
+}
+
+/// Every note in the vault with its frontmatter, sorted like
+/// `list_markdown_files_impl`.
+pub fn list_notes_with_metadata_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+) -> VaultResult<Vec<NoteEntry>> {
+    store.with_index(vault_path, |_, index| {
+        let mut entries: Vec<NoteEntry> = index
+            .notes
+            .iter()
+            .map(|(rel_path, note)| NoteEntry {
+                rel_path: rel_path.clone(),
+                display_name: display_name_for_path(Path::new(rel_path)),
+                metadata: Some(note.metadata.clone()),
+            })
+            .collect();
+        sort_note_entries(&mut entries);
+        entries
+    })
+}
+
+pub fn find_alias_collisions_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+) -> VaultResult<Vec<AliasCollision>> {
+    store.with_index(vault_path, |_, index| index.alias_collisions())
+}
+
+pub fn list_unresolved_links_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    show_hidden: bool,
+) -> VaultResult<Vec<UnresolvedLink>> {
+    store.with_index(vault_path, |_, index| {
+        index.unresolved_links(|rel_path| show_hidden || !is_hidden_path(rel_path))
+    })
+}
+
+pub fn find_outgoing_links_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    rel_path: &str,
+) -> VaultResult<Vec<OutgoingLink>> {
+    store
+        .with_index(vault_path, |_, index| index.outgoing_links(rel_path))?
+        .ok_or_else(|| VaultError::NotFound("note not found".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rel_path_key, AliasCollision, IndexedNote, LinkIndex, LinkIndexStore};
+    use crate::testing::TempDir;
+
+    fn note(links: &[&str]) -> IndexedNote {
+        IndexedNote {
+            mtime_ms: 0,
+            size: 0,
+            created_ms: None,
+            links: links.iter().map(|l| l.to_string()).collect(),
+            metadata: Default::default(),
+            external_links: Vec::new(),
+            tags: Vec::new(),
+            words: Vec::new(),
+        }
+    }
+
+    fn aliased(links: &[&str], aliases: &[&str]) -> IndexedNote {
+        let mut note = note(links);
+        note.metadata.aliases = aliases.iter().map(|a| a.to_string()).collect();
+        note
+    }
+
+    #[test]
+    fn rel_path_keys() {
+        assert_eq!(rel_path_key("Folder/Note.md"), "folder/note");
+        assert_eq!(rel_path_key("Note.markdown"), "note");
+    }
+
+    #[test]
+    fn resolves_stems_and_paths() {
+        let mut index = LinkIndex::new();
+        index
+            .notes
+            .insert("a/Foo.md".into(), note(&["bar", "b/bar", "missing"]));
+        index.notes.insert("b/Bar.md".into(), note(&["foo"]));
+        index.notes.insert("c/Bar.md".into(), note(&["/a/foo"]));
+
+        let resolver = index.resolver();
+        assert_eq!(resolver.resolve("a/Foo.md", "bar"), Some("b/Bar.md"));
+        assert_eq!(resolver.resolve("c/Other.md", "bar"), Some("c/Bar.md"));
+        assert_eq!(resolver.resolve("a/Foo.md", "c/bar"), Some("c/Bar.md"));
+
+        let outgoing = index.outgoing_links("a/Foo.md").unwrap();
+        let resolved: Vec<Option<&str>> = outgoing.iter().map(|l| l.rel_path.as_deref()).collect();
+        assert_eq!(resolved, vec![Some("b/Bar.md"), Some("b/Bar.md"), None]);
+
+        assert_eq!(index.backlinks("foo"), vec!["b/Bar.md".to_string()]);
+        assert_eq!(
+            index.resolved_backlinks("a/Foo.md"),
+            vec!["b/Bar.md", "c/Bar.md"]
+        );
+
+        index
+            .notes
+            .insert("d.md".into(), note(&["missing", "other"]));
+        let unresolved = index.unresolved_links(|_| true);
+        assert_eq!(unresolved[0].target, "missing");
+        assert_eq!(unresolved[0].sources, vec!["a/Foo.md", "d.md"]);
+        assert_eq!(unresolved[0].count, 2);
+        assert_eq!(unresolved[1].target, "other");
+        assert!(index.unresolved_links(|p| p != "d.md")[0].count == 1);
+    }
+
+    #[test]
+    fn skips_attachments_in_unresolved_links() {
+        let mut index = LinkIndex::new();
+        index.notes.insert(
+            "Note.md".into(),
+            note(&[
+                "photo.png",
+                "/attachments/shot.png",
+                "release 1.2",
+                "missing",
+            ]),
+        );
+        let targets: Vec<String> = index
+            .unresolved_links(|_| true)
+            .into_iter()
+            .map(|link| link.target)
+            .collect();
+        assert_eq!(targets, vec!["missing", "release 1.2"]);
+    }
+
+    #[test]
+    fn resolves_aliases_without_shadowing_names() {
+        let mut index = LinkIndex::new();
+        index
+            .notes
+            .insert("JavaScript.md".into(), aliased(&[], &["JS", "Script"]));
+        index
+            .notes
+            .insert("Other.md".into(), aliased(&["js"], &["script"]));
+        index.notes.insert("Script.md".into(), note(&[]));
+
+        let resolver = index.resolver();
+        assert_eq!(resolver.resolve("", "js"), Some("JavaScript.md"));
+        assert_eq!(resolver.resolve("", "script"), Some("Script.md"));
+        assert_eq!(index.resolved_backlinks("JavaScript.md"), vec!["Other.md"]);
+
+        assert_eq!(
+            index.alias_collisions(),
+            vec![AliasCollision {
+                alias: "script".into(),
+                declared_by: vec!["JavaScript.md".into(), "Other.md".into()],
+                resolved_to: "Script.md".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn store_updates_changed_notes_without_rescanning() {
+        let dir = TempDir::new("index-store");
+        std::fs::create_dir_all(dir.join("folder")).unwrap();
+        std::fs::write(dir.join("A.md"), "[[B]]").unwrap();
+        std::fs::write(dir.join("folder/B.md"), "").unwrap();
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+        let notes = || -> Vec<String> {
+            store
+                .with_index(&vault, |_, index| {
+                    index.notes().map(|(p, _)| p.clone()).collect()
+                })
+                .unwrap()
+        };
+        assert_eq!(notes(), vec!["A.md", "folder/B.md"]);
+
+        // Queries trust the index; only reported changes are picked up.
+        std::fs::write(dir.join("C.md"), "").unwrap();
+        std::fs::write(dir.join("A.md"), "[[C]]").unwrap();
+        assert_eq!(notes(), vec!["A.md", "folder/B.md"]);
+        store.notes_changed(&vault, &["C.md".to_string(), "A.md".to_string()]);
+        let links = store
+            .with_index(&vault, |_, index| index.note("A.md").unwrap().links.clone())
+            .unwrap();
+        assert_eq!(links, vec!["c"]);
+
+        std::fs::rename(dir.join("folder"), dir.join("moved")).unwrap();
+        store.notes_changed(&vault, &["folder".to_string(), "moved".to_string()]);
+        assert_eq!(notes(), vec!["A.md", "C.md", "moved/B.md"]);
+    }
+}
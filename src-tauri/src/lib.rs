@@ -1,8 +1,27 @@
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
+mod attachments;
 mod backlinks;
+mod error;
+mod frontmatter;
 mod graph;
+mod index;
+mod mentions;
+mod merge;
+mod protocol;
+mod rename;
+mod resolver;
+mod search;
+mod tags;
+#[cfg(test)]
+mod testing;
+mod thumbnails;
+mod trash;
+mod tree;
 mod vault;
+mod walk;
+mod watcher;
+mod wikilinks;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -16,6 +35,21 @@ pub fn run() {
                 )?;
             }
 
+            let index_dir = app
+                .path()
+                .app_config_dir()
+                .map(|dir| dir.join("link-index"))
+                .ok();
+            app.manage(LinkIndexStore::new(index_dir));
+            let thumbnail_dir = app
+                .path()
+                .app_cache_dir()
+                .map(|dir| dir.join("thumbnails"))
+                .ok();
+            app.manage(ThumbnailCache::new(thumbnail_dir));
+            app.manage(SearchIndexStore::default());
+            app.manage(VaultWatcher::default());
+
             let window = app
                 .get_webview_window("main")
                 .ok_or("missing main window")?;
@@ -59,14 +93,46 @@ pub fn run() {
             Ok(())
         })
         .plugin(tauri_plugin_dialog::init())
+        .register_asynchronous_uri_scheme_protocol(VAULT_PROTOCOL, |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(vault_asset_response(&app, &request));
+            });
+        })
         .invoke_handler(tauri::generate_handler![
             list_markdown_files,
+            list_vault_tree,
             read_note,
             write_note,
+            get_note_metadata,
+            set_note_metadata,
+            merge_note_versions,
             create_note,
+            rename_note,
+            delete_note,
+            create_folder,
+            rename_folder,
+            delete_folder,
+            restore_note,
+            list_trash,
+            purge_trash,
             find_backlinks,
+            find_outgoing_links,
+            find_alias_collisions,
+            list_unresolved_links,
+            list_ambiguous_links,
+            find_unlinked_mentions,
+            link_mention,
+            search_notes,
+            list_tags,
+            notes_with_tag,
             read_vault_image,
+            import_attachment,
+            list_attachments,
+            find_orphan_attachments,
             build_graph,
+            watch_vault,
+            unwatch_vault,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -113,60 +179,632 @@ fn persist_window_state(
     std::fs::write(path, json)
 }
 
-use crate::backlinks::find_backlinks_impl;
+/// Re-index notes a command just changed, given by rel_path or folder, so
+/// queries see the change without waiting for the watcher or rescanning the
+/// vault.
+fn notes_changed(app: &tauri::AppHandle, vault_path: &str, rel_paths: &[String]) {
+    let Ok(vault) = resolve_vault(vault_path) else {
+        return;
+    };
+    let rel_paths: Vec<String> = rel_paths
+        .iter()
+        .filter_map(|rel_path| {
+            let clean = sanitize_rel_path(rel_path).ok()?;
+            path_to_rel_string(&vault, &vault.join(clean)).ok()
+        })
+        .collect();
+    app.state::<LinkIndexStore>()
+        .notes_changed(vault_path, &rel_paths);
+    app.state::<SearchIndexStore>()
+        .notes_changed(vault_path, &rel_paths);
+}
+
+/// Serve a `vault://` request, see `protocol::VAULT_PROTOCOL`.
+fn vault_asset_response(
+    app: &tauri::AppHandle,
+    request: &tauri::http::Request<Vec<u8>>,
+) -> tauri::http::Response<Vec<u8>> {
+    let header = |name: tauri::http::header::HeaderName| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    // The root comes from the vault being watched, never from the URL alone.
+    let vault = app.state::<VaultWatcher>().vault();
+    let app_origin = app
+        .get_webview_window("main")
+        .and_then(|window| window.url().ok())
+        .map(|url| url.origin().ascii_serialization());
+    let asset = serve_vault_asset(
+        &app.state::<ThumbnailCache>(),
+        &AssetScope {
+            vault: vault.as_deref(),
+            app_origin: app_origin.as_deref(),
+        },
+        &AssetRequest {
+            method: request.method().as_str(),
+            origin: header(tauri::http::header::ORIGIN),
+            path: request.uri().path(),
+            query: request.uri().query(),
+            range: header(tauri::http::header::RANGE),
+            if_range: header(tauri::http::header::IF_RANGE),
+            if_none_match: header(tauri::http::header::IF_NONE_MATCH),
+            if_modified_since: header(tauri::http::header::IF_MODIFIED_SINCE),
+        },
+    );
+
+    let mut response = tauri::http::Response::builder().status(asset.status);
+    for (name, value) in asset.headers {
+        response = response.header(name, value);
+    }
+    response.body(asset.body).unwrap_or_else(|e| {
+        log::error!("failed to build vault asset response: {e}");
+        let mut fallback = tauri::http::Response::new(Vec::new());
+        *fallback.status_mut() = tauri::http::StatusCode::INTERNAL_SERVER_ERROR;
+        fallback
+    })
+}
+
+use crate::attachments::{
+    find_orphan_attachments_impl, import_attachment_impl, list_attachments_impl, Attachment,
+    ImportedAttachment,
+};
+use crate::backlinks::{find_backlinks_impl, Backlink};
+use crate::error::VaultError;
+use crate::frontmatter::{get_note_metadata_impl, set_note_metadata_impl, NoteMetadata};
 use crate::graph::{build_graph_impl, GraphData, GraphOptions};
+use crate::index::{
+    find_alias_collisions_impl, find_outgoing_links_impl, list_notes_with_metadata_impl,
+    list_unresolved_links_impl, AliasCollision, LinkIndexStore, OutgoingLink, UnresolvedLink,
+};
+use crate::mentions::{find_unlinked_mentions_impl, link_mention_impl, UnlinkedMention};
+use crate::merge::{merge_note_versions_impl, MergeGranularity, MergeResult};
+use crate::protocol::{
+    percent_decode, serve_vault_asset, AssetRequest, AssetScope, VAULT_PROTOCOL,
+};
+use crate::rename::{rename_folder_impl, rename_note_impl, RenameReport};
+use crate::resolver::{list_ambiguous_links_impl, AmbiguousLink};
+use crate::search::{search_notes_impl, SearchHit, SearchIndexStore, SearchOptions};
+use crate::tags::{list_tags_impl, notes_with_tag_impl, TagNode};
+use crate::thumbnails::{read_vault_thumbnail_impl, ThumbnailCache};
+use crate::trash::{
+    delete_folder_impl, delete_note_impl, list_trash_impl, purge_trash_impl, restore_note_impl,
+    DeleteReport, TrashEntry,
+};
+use crate::tree::{list_vault_tree_impl, TreeEntry};
 use crate::vault::{
-    create_note_impl, list_markdown_files_impl, read_note_impl, read_vault_image_impl,
-    write_note_impl, NoteEntry, VaultImage,
+    create_folder_impl, create_note_impl, path_to_rel_string, read_note_impl,
+    read_vault_image_impl, resolve_vault, sanitize_rel_path, write_note_impl, NoteContents,
+    NoteEntry, NoteVersion, VaultImage,
 };
+use crate::watcher::{VaultWatcher, VAULT_CHANGED_EVENT};
 
 #[tauri::command(rename = "list-markdown-files")]
-async fn list_markdown_files(vault_path: String) -> Result<Vec<NoteEntry>, String> {
-    tauri::async_runtime::spawn_blocking(move || list_markdown_files_impl(&vault_path))
-        .await
-        .map_err(|e| format!("failed to join task: {e}"))?
+async fn list_markdown_files(
+    app: tauri::AppHandle,
+    vault_path: String,
+) -> Result<Vec<NoteEntry>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_notes_with_metadata_impl(&app.state::<LinkIndexStore>(), &vault_path)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "list-vault-tree")]
+async fn list_vault_tree(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: Option<String>,
+    depth: Option<usize>,
+    show_hidden: bool,
+) -> Result<TreeEntry, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_vault_tree_impl(
+            &app.state::<LinkIndexStore>(),
+            &vault_path,
+            rel_path.as_deref(),
+            depth,
+            show_hidden,
+        )
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
 }
 
 #[tauri::command(rename = "read-note")]
-async fn read_note(vault_path: String, rel_path: String) -> Result<String, String> {
+async fn read_note(vault_path: String, rel_path: String) -> Result<NoteContents, VaultError> {
     tauri::async_runtime::spawn_blocking(move || read_note_impl(&vault_path, &rel_path))
         .await
-        .map_err(|e| format!("failed to join task: {e}"))?
+        .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
 }
 
 #[tauri::command(rename = "write-note")]
-async fn write_note(vault_path: String, rel_path: String, contents: String) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || write_note_impl(&vault_path, &rel_path, &contents))
+async fn write_note(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: String,
+    contents: String,
+    expected: Option<NoteVersion>,
+) -> Result<NoteVersion, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<NoteVersion, VaultError> {
+        let version = write_note_impl(&vault_path, &rel_path, &contents, expected.as_ref())?;
+        notes_changed(&app, &vault_path, &[rel_path]);
+        Ok(version)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "get-note-metadata")]
+async fn get_note_metadata(
+    vault_path: String,
+    rel_path: String,
+) -> Result<NoteMetadata, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || get_note_metadata_impl(&vault_path, &rel_path))
         .await
-        .map_err(|e| format!("failed to join task: {e}"))?
+        .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "set-note-metadata")]
+async fn set_note_metadata(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: String,
+    key: String,
+    value: Option<serde_json::Value>,
+    expected: Option<NoteVersion>,
+) -> Result<NoteVersion, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<NoteVersion, VaultError> {
+        let version =
+            set_note_metadata_impl(&vault_path, &rel_path, &key, value, expected.as_ref())?;
+        notes_changed(&app, &vault_path, &[rel_path]);
+        Ok(version)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "merge-note-versions")]
+async fn merge_note_versions(
+    base: String,
+    ours: String,
+    theirs: String,
+    granularity: Option<MergeGranularity>,
+) -> Result<MergeResult, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        merge_note_versions_impl(&base, &ours, &theirs, granularity)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))
 }
 
 #[tauri::command(rename = "create-note")]
-async fn create_note(vault_path: String, rel_path: String, contents: String) -> Result<(), String> {
+async fn create_note(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: String,
+    contents: String,
+) -> Result<(), VaultError> {
     tauri::async_runtime::spawn_blocking(move || {
-        create_note_impl(&vault_path, &rel_path, &contents)
+        create_note_impl(&vault_path, &rel_path, &contents)?;
+        notes_changed(&app, &vault_path, &[rel_path]);
+        Ok(())
     })
     .await
-    .map_err(|e| format!("failed to join task: {e}"))?
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
 }
 
-#[tauri::command(rename = "find-backlinks")]
-async fn find_backlinks(vault_path: String, target_title: String) -> Result<Vec<String>, String> {
-    tauri::async_runtime::spawn_blocking(move || find_backlinks_impl(&vault_path, &target_title))
+#[tauri::command(rename = "rename-note")]
+async fn rename_note(
+    app: tauri::AppHandle,
+    vault_path: String,
+    from: String,
+    to: String,
+) -> Result<RenameReport, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = rename_note_impl(&app.state::<LinkIndexStore>(), &vault_path, &from, &to)?;
+        notes_changed(&app, &vault_path, &report.changed_paths());
+        Ok(report)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "delete-note")]
+async fn delete_note(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: String,
+) -> Result<DeleteReport, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = delete_note_impl(&app.state::<LinkIndexStore>(), &vault_path, &rel_path)?;
+        notes_changed(&app, &vault_path, &[report.entry.original_rel_path.clone()]);
+        Ok(report)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "create-folder")]
+async fn create_folder(vault_path: String, rel_path: String) -> Result<String, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || create_folder_impl(&vault_path, &rel_path))
         .await
-        .map_err(|e| format!("failed to join task: {e}"))?
+        .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
 }
 
-#[tauri::command(rename = "read-vault-image")]
-async fn read_vault_image(vault_path: String, rel_path: String) -> Result<VaultImage, String> {
-    tauri::async_runtime::spawn_blocking(move || read_vault_image_impl(&vault_path, &rel_path))
+#[tauri::command(rename = "rename-folder")]
+async fn rename_folder(
+    app: tauri::AppHandle,
+    vault_path: String,
+    from: String,
+    to: String,
+) -> Result<RenameReport, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = rename_folder_impl(&app.state::<LinkIndexStore>(), &vault_path, &from, &to)?;
+        notes_changed(&app, &vault_path, &report.changed_paths());
+        Ok(report)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "delete-folder")]
+async fn delete_folder(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: String,
+) -> Result<DeleteReport, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = delete_folder_impl(&app.state::<LinkIndexStore>(), &vault_path, &rel_path)?;
+        notes_changed(&app, &vault_path, &[report.entry.original_rel_path.clone()]);
+        Ok(report)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "restore-note")]
+async fn restore_note(
+    app: tauri::AppHandle,
+    vault_path: String,
+    id: String,
+) -> Result<TrashEntry, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let entry = restore_note_impl(&vault_path, &id)?;
+        notes_changed(&app, &vault_path, &[entry.original_rel_path.clone()]);
+        Ok(entry)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "list-trash")]
+async fn list_trash(vault_path: String) -> Result<Vec<TrashEntry>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || list_trash_impl(&vault_path))
         .await
-        .map_err(|e| format!("failed to join task: {e}"))?
+        .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
 }
 
-#[tauri::command(rename = "build-graph")]
-async fn build_graph(vault_path: String, options: GraphOptions) -> Result<GraphData, String> {
-    tauri::async_runtime::spawn_blocking(move || build_graph_impl(&vault_path, options))
+#[tauri::command(rename = "purge-trash")]
+async fn purge_trash(
+    vault_path: String,
+    ids: Option<Vec<String>>,
+) -> Result<Vec<String>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || purge_trash_impl(&vault_path, ids))
         .await
-        .map_err(|e| format!("failed to join task: {e}"))?
+        .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "find-backlinks")]
+async fn find_backlinks(
+    app: tauri::AppHandle,
+    vault_path: String,
+    target_title: String,
+    rel_path: Option<String>,
+) -> Result<Vec<Backlink>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        find_backlinks_impl(
+            &app.state::<LinkIndexStore>(),
+            &vault_path,
+            &target_title,
+            rel_path.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "find-outgoing-links")]
+async fn find_outgoing_links(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: String,
+) -> Result<Vec<OutgoingLink>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        find_outgoing_links_impl(&app.state::<LinkIndexStore>(), &vault_path, &rel_path)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "find-alias-collisions")]
+async fn find_alias_collisions(
+    app: tauri::AppHandle,
+    vault_path: String,
+) -> Result<Vec<AliasCollision>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        find_alias_collisions_impl(&app.state::<LinkIndexStore>(), &vault_path)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "list-unresolved-links")]
+async fn list_unresolved_links(
+    app: tauri::AppHandle,
+    vault_path: String,
+    show_hidden: bool,
+) -> Result<Vec<UnresolvedLink>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_unresolved_links_impl(&app.state::<LinkIndexStore>(), &vault_path, show_hidden)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "list-ambiguous-links")]
+async fn list_ambiguous_links(
+    app: tauri::AppHandle,
+    vault_path: String,
+    show_hidden: bool,
+) -> Result<Vec<AmbiguousLink>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_ambiguous_links_impl(&app.state::<LinkIndexStore>(), &vault_path, show_hidden)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "find-unlinked-mentions")]
+async fn find_unlinked_mentions(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: String,
+    show_hidden: bool,
+) -> Result<Vec<UnlinkedMention>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        find_unlinked_mentions_impl(
+            &app.state::<LinkIndexStore>(),
+            &vault_path,
+            &rel_path,
+            show_hidden,
+        )
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "link-mention")]
+async fn link_mention(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: String,
+    start: usize,
+    end: usize,
+    target_rel_path: String,
+    expected: Option<NoteVersion>,
+) -> Result<NoteVersion, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<NoteVersion, VaultError> {
+        let version = link_mention_impl(
+            &app.state::<LinkIndexStore>(),
+            &vault_path,
+            &rel_path,
+            start..end,
+            &target_rel_path,
+            expected.as_ref(),
+        )?;
+        notes_changed(&app, &vault_path, &[rel_path]);
+        Ok(version)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "search-notes")]
+async fn search_notes(
+    app: tauri::AppHandle,
+    vault_path: String,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<Vec<SearchHit>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        search_notes_impl(
+            &app.state::<SearchIndexStore>(),
+            &vault_path,
+            &query,
+            options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "list-tags")]
+async fn list_tags(
+    app: tauri::AppHandle,
+    vault_path: String,
+    show_hidden: bool,
+) -> Result<Vec<TagNode>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_tags_impl(&app.state::<LinkIndexStore>(), &vault_path, show_hidden)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "notes-with-tag")]
+async fn notes_with_tag(
+    app: tauri::AppHandle,
+    vault_path: String,
+    tag: String,
+    show_hidden: bool,
+) -> Result<Vec<String>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        notes_with_tag_impl(
+            &app.state::<LinkIndexStore>(),
+            &vault_path,
+            &tag,
+            show_hidden,
+        )
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "read-vault-image")]
+async fn read_vault_image(
+    app: tauri::AppHandle,
+    vault_path: String,
+    rel_path: String,
+    max_dimension: Option<u32>,
+) -> Result<VaultImage, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || match max_dimension {
+        Some(max_dimension) => read_vault_thumbnail_impl(
+            &app.state::<ThumbnailCache>(),
+            &vault_path,
+            &rel_path,
+            max_dimension,
+        ),
+        None => read_vault_image_impl(&vault_path, &rel_path),
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+/// The file arrives as the raw request body, so large attachments skip JSON
+/// encoding. The other arguments travel in percent-encoded headers.
+#[tauri::command(rename = "import-attachment")]
+async fn import_attachment(
+    request: tauri::ipc::Request<'_>,
+) -> Result<ImportedAttachment, VaultError> {
+    let tauri::ipc::InvokeBody::Raw(bytes) = request.body() else {
+        return Err(VaultError::Invalid(
+            "attachment must be sent as raw bytes".to_string(),
+        ));
+    };
+    let header = |name: &str| -> Result<Option<String>, VaultError> {
+        request
+            .headers()
+            .get(name)
+            .map(|value| {
+                value
+                    .to_str()
+                    .ok()
+                    .and_then(percent_decode)
+                    .ok_or_else(|| VaultError::Invalid(format!("malformed `{name}` header")))
+            })
+            .transpose()
+    };
+    let required = |name: &str| -> Result<String, VaultError> {
+        header(name)?.ok_or_else(|| VaultError::Invalid(format!("missing `{name}` header")))
+    };
+    let vault_path = required("vault-path")?;
+    let file_name = required("file-name")?;
+    let note_rel_path = header("note-rel-path")?;
+    let folder = header("folder")?;
+    let bytes = bytes.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        import_attachment_impl(
+            &vault_path,
+            note_rel_path.as_deref(),
+            &file_name,
+            &bytes,
+            folder.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "list-attachments")]
+async fn list_attachments(
+    app: tauri::AppHandle,
+    vault_path: String,
+    show_hidden: bool,
+) -> Result<Vec<Attachment>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_attachments_impl(&app.state::<LinkIndexStore>(), &vault_path, show_hidden)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "find-orphan-attachments")]
+async fn find_orphan_attachments(
+    app: tauri::AppHandle,
+    vault_path: String,
+    show_hidden: bool,
+) -> Result<Vec<Attachment>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        find_orphan_attachments_impl(&app.state::<LinkIndexStore>(), &vault_path, show_hidden)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "build-graph")]
+async fn build_graph(
+    app: tauri::AppHandle,
+    vault_path: String,
+    options: GraphOptions,
+) -> Result<GraphData, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        build_graph_impl(&app.state::<LinkIndexStore>(), &vault_path, options)
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "watch-vault")]
+async fn watch_vault(
+    app: tauri::AppHandle,
+    vault_path: String,
+    show_hidden: bool,
+) -> Result<(), VaultError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        // Catch up on anything changed while the vault was not watched.
+        app.state::<LinkIndexStore>().refresh(&vault_path);
+        app.state::<SearchIndexStore>().refresh(&vault_path);
+        let handle = app.clone();
+        app.state::<VaultWatcher>()
+            .watch(&vault_path, show_hidden, move |vault, changes| {
+                handle
+                    .state::<LinkIndexStore>()
+                    .apply_changes(vault, &changes);
+                handle
+                    .state::<SearchIndexStore>()
+                    .apply_changes(vault, &changes);
+                let changes = if show_hidden {
+                    changes
+                } else {
+                    changes.without_hidden()
+                };
+                if changes.is_empty() {
+                    return;
+                }
+                if let Err(e) = handle.emit(VAULT_CHANGED_EVENT, &changes) {
+                    log::warn!("failed to emit vault changes: {e}");
+                }
+            })
+    })
+    .await
+    .map_err(|e| VaultError::other(format!("failed to join task: {e}")))?
+}
+
+#[tauri::command(rename = "unwatch-vault")]
+fn unwatch_vault(app: tauri::AppHandle) {
+    app.state::<VaultWatcher>().unwatch();
 }
@@ -0,0 +1,373 @@
+use crate::backlinks::occurrence_snippet;
+use crate::error::{VaultError, VaultResult};
+use crate::frontmatter::body_start;
+use crate::graph::is_hidden_path;
+use crate::index::{rel_path_key, LinkIndex, LinkIndexStore};
+use crate::rename::strip_markdown_extension;
+use crate::vault::{display_name_for_path, read_note_impl, write_note_impl, NoteVersion};
+use crate::wikilinks::{
+    code_span_ranges, fenced_code_ranges, parse_markdown_links, parse_wikilinks,
+};
+use serde::Serialize;
+use std::ops::Range;
+use std::path::Path;
+
+/// A plain-text mention of a note's title or alias.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MentionOccurrence {
+    /// 1-based line number of the mention
+    pub line: usize,
+    /// Byte range of the mention in the note
+    pub range: Range<usize>,
+    /// Paragraph around the mention, cut down to a readable length
+    pub snippet: String,
+    /// Byte range of the mention within `snippet`
+    pub highlight: Range<usize>,
+}
+
+/// A note mentioning the target note without linking to it.
+#[derive(Debug, Serialize)]
+pub struct UnlinkedMention {
+    pub rel_path: String,
+    pub occurrences: Vec<MentionOccurrence>,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Words of `text`, lowercased a character at a time like `match_len`.
+fn lowercase_words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !is_word_char(c))
+        .filter(|word| !word.is_empty())
+        .map(|word| word.chars().flat_map(char::to_lowercase).collect())
+}
+
+/// Sorted, deduplicated words of a note, stored in the link index. A note can
+/// only mention a term if it contains every word of it.
+pub(crate) fn note_words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = lowercase_words(text).collect();
+    words.sort_unstable();
+    words.dedup();
+    words
+}
+
+/// Whether `text` can sit inside `[[...]]` unchanged. The link syntax has no
+/// escapes, so a `|` or `#` would split the link and brackets would end it.
+fn fits_in_wikilink(text: &str) -> bool {
+    !text.contains(['|', '#']) && !text.contains("[[") && !text.contains("]]")
+}
+
+/// Byte ranges that never count as mentions: frontmatter, code, existing
+/// links and bare URLs.
+fn excluded_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = fenced_code_ranges(text)
+        .into_iter()
+        .map(|(s, e)| s..e)
+        .collect();
+    ranges.push(0..body_start(text));
+    ranges.extend(parse_wikilinks(text).into_iter().map(|link| link.range));
+    ranges.extend(
+        parse_markdown_links(text)
+            .into_iter()
+            .map(|link| link.range),
+    );
+
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        ranges.extend(
+            code_span_ranges(line)
+                .into_iter()
+                .map(|(s, e)| offset + s..offset + e),
+        );
+        let mut word_start = 0;
+        for word in line.split_inclusive(char::is_whitespace) {
+            if word.contains("://") {
+                ranges.push(offset + word_start..offset + word_start + word.len());
+            }
+            word_start += word.len();
+        }
+        offset += line.len();
+    }
+    ranges
+}
+
+/// Length in bytes of `term` at the start of `hay`, compared case-insensitively.
+fn match_len(hay: &str, term: &str) -> Option<usize> {
+    let mut chars = hay.char_indices();
+    for t in term.chars() {
+        let (_, c) = chars.next()?;
+        if !c.to_lowercase().eq(t.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(hay.len(), |(idx, _)| idx))
+}
+
+/// Whole-word, case-insensitive occurrences of any of `terms` outside code
+/// and links. `terms` must be sorted longest first so the longest name wins.
+fn find_mentions(text: &str, terms: &[String]) -> Vec<Range<usize>> {
+    let excluded = excluded_ranges(text);
+    let mut found: Vec<Range<usize>> = Vec::new();
+    let mut prev: Option<char> = None;
+
+    for (idx, c) in text.char_indices() {
+        let at_boundary = prev.map_or(true, |p| !is_word_char(p));
+        prev = Some(c);
+        if !at_boundary || found.last().is_some_and(|last| idx < last.end) {
+            continue;
+        }
+
+        for term in terms {
+            let Some(len) = match_len(&text[idx..], term) else {
+                continue;
+            };
+            let end = idx + len;
+            let ends_word = text[end..]
+                .chars()
+                .next()
+                .map_or(true, |n| !is_word_char(n));
+            if ends_word && !excluded.iter().any(|r| r.start < end && idx < r.end) {
+                found.push(idx..end);
+                break;
+            }
+        }
+    }
+    found
+}
+
+/// Names the note at `rel_path` is mentioned by, longest first, and the
+/// wikilink target that resolves to it from the note at `source`.
+fn mention_terms(
+    index: &LinkIndex,
+    rel_path: &str,
+    source: &str,
+) -> VaultResult<(Vec<String>, String)> {
+    let note = index
+        .note(rel_path)
+        .ok_or_else(|| VaultError::NotFound("note not found".to_string()))?;
+
+    let name = display_name_for_path(Path::new(rel_path));
+    let stem_resolves = index.resolver().resolve(source, &rel_path_key(&name)) == Some(rel_path);
+    let link_target = if stem_resolves {
+        name.clone()
+    } else {
+        strip_markdown_extension(rel_path).to_string()
+    };
+
+    let mut terms = vec![name];
+    for alias in &note.metadata.aliases {
+        let alias = alias.trim();
+        if !alias.is_empty()
+            && !terms
+                .iter()
+                .any(|t| t.to_lowercase() == alias.to_lowercase())
+        {
+            terms.push(alias.to_string());
+        }
+    }
+    terms.sort_by_key(|term| std::cmp::Reverse(term.chars().count()));
+    Ok((terms, link_target))
+}
+
+/// Notes mentioning the note at `rel_path` by title or alias without linking
+/// to it, in rel_path order.
+pub fn find_unlinked_mentions_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    rel_path: &str,
+    show_hidden: bool,
+) -> VaultResult<Vec<UnlinkedMention>> {
+    let (vault, terms, sources) = store.with_index(vault_path, |vault, index| {
+        let (mut terms, link_target) = mention_terms(index, rel_path, "")?;
+        if !fits_in_wikilink(&link_target) {
+            return Ok((vault.to_path_buf(), Vec::new(), Vec::new()));
+        }
+        terms.retain(|term| fits_in_wikilink(term));
+
+        // Only notes holding every word of some term are read.
+        let term_words: Vec<Vec<String>> = terms
+            .iter()
+            .map(|term| lowercase_words(term).collect())
+            .collect();
+        let sources: Vec<String> = index
+            .notes()
+            .filter(|(source, _)| *source != rel_path)
+            .filter(|(source, _)| show_hidden || !is_hidden_path(source))
+            .filter(|(_, note)| {
+                term_words.iter().any(|words| {
+                    words
+                        .iter()
+                        .all(|word| note.words.binary_search(word).is_ok())
+                })
+            })
+            .map(|(source, _)| source.clone())
+            .collect();
+        Ok::<_, VaultError>((vault.to_path_buf(), terms, sources))
+    })??;
+
+    let mut mentions = Vec::new();
+    for source in sources {
+        let text = match std::fs::read_to_string(vault.join(&source)) {
+            Ok(text) => text,
+            Err(e) => {
+                log::warn!("failed to read mentions in {source}: {e}");
+                continue;
+            }
+        };
+        let occurrences: Vec<MentionOccurrence> = find_mentions(&text, &terms)
+            .into_iter()
+            .map(|range| {
+                let (snippet, highlight) = occurrence_snippet(&text, &range);
+                MentionOccurrence {
+                    line: text[..range.start].matches('\n').count() + 1,
+                    range,
+                    snippet,
+                    highlight,
+                }
+            })
+            .collect();
+        if !occurrences.is_empty() {
+            mentions.push(UnlinkedMention {
+                rel_path: source,
+                occurrences,
+            });
+        }
+    }
+    Ok(mentions)
+}
+
+/// Turn the mention at `range` of the note at `rel_path` into a wikilink to
+/// `target_rel_path`, keeping the mentioned text as the alias when it differs
+/// from the link target. The write is refused with a conflict if the note
+/// changed on disk since `expected`, or no longer has a mention at `range`.
+pub fn link_mention_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    rel_path: &str,
+    range: Range<usize>,
+    target_rel_path: &str,
+    expected: Option<&NoteVersion>,
+) -> VaultResult<NoteVersion> {
+    let (terms, link_target) = store.with_index(vault_path, |_, index| {
+        mention_terms(index, target_rel_path, rel_path)
+    })??;
+
+    let note = read_note_impl(vault_path, rel_path)?;
+    if let Some(expected) = expected {
+        if note.version.hash != expected.hash {
+            return Err(VaultError::Conflict(note));
+        }
+    }
+
+    // A range that no longer covers a mention means the note moved on since
+    // the mentions were listed; report it like any other stale write.
+    if !find_mentions(&note.contents, &terms).contains(&range) {
+        return Err(VaultError::Conflict(note));
+    }
+    let text = &note.contents;
+    let mention = &text[range.clone()];
+    if !fits_in_wikilink(mention) || !fits_in_wikilink(&link_target) {
+        return Err(VaultError::Invalid(
+            "this mention cannot be written as a wikilink".to_string(),
+        ));
+    }
+    let link = if mention == link_target {
+        format!("[[{mention}]]")
+    } else {
+        format!("[[{link_target}|{mention}]]")
+    };
+    let updated = format!("{}{link}{}", &text[..range.start], &text[range.end..]);
+    write_note_impl(vault_path, rel_path, &updated, Some(&note.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_mentions, find_unlinked_mentions_impl, link_mention_impl};
+    use crate::error::VaultError;
+    use crate::index::LinkIndexStore;
+    use crate::testing::TempDir;
+    use crate::vault::read_note_impl;
+
+    #[test]
+    fn finds_whole_word_mentions_outside_links_and_code() {
+        let terms = vec!["JavaScript Guide".to_string(), "JavaScript".to_string()];
+        let text = "---\ntitle: JavaScript\n---\nI like javascript and the JavaScript guide.\n\
+                    Not JavaScripts, [[JavaScript]], `JavaScript` or https://x.dev/javascript\n";
+        let found: Vec<&str> = find_mentions(text, &terms)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(found, vec!["javascript", "JavaScript guide"]);
+    }
+
+    #[test]
+    fn links_mentions_in_place() {
+        let dir = TempDir::new("link-mention");
+        std::fs::write(dir.join("Target.md"), "target").unwrap();
+        std::fs::write(dir.join("Note.md"), "About Target and target.").unwrap();
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+
+        link_mention_impl(&store, &vault, "Note.md", 6..12, "Target.md", None).unwrap();
+        let note = read_note_impl(&vault, "Note.md").unwrap();
+        assert_eq!(note.contents, "About [[Target]] and target.");
+
+        link_mention_impl(&store, &vault, "Note.md", 21..27, "Target.md", None).unwrap();
+        let note = read_note_impl(&vault, "Note.md").unwrap();
+        assert_eq!(note.contents, "About [[Target]] and [[Target|target]].");
+
+        // The first mention is now a link, so its old range is stale.
+        match link_mention_impl(&store, &vault, "Note.md", 6..12, "Target.md", None) {
+            Err(VaultError::Conflict(on_disk)) => assert_eq!(on_disk.contents, note.contents),
+            other => panic!("expected conflict, got {other:?}"),
+        }
+        std::fs::write(dir.join("Note.md"), "Target, edited elsewhere").unwrap();
+        match link_mention_impl(
+            &store,
+            &vault,
+            "Note.md",
+            0..6,
+            "Target.md",
+            Some(&note.version),
+        ) {
+            Err(VaultError::Conflict(on_disk)) => {
+                assert_eq!(on_disk.contents, "Target, edited elsewhere")
+            }
+            other => panic!("expected conflict, got {other:?}"),
+        }
+        assert_eq!(
+            std::fs::read_to_string(dir.join("Note.md")).unwrap(),
+            "Target, edited elsewhere"
+        );
+    }
+
+    #[test]
+    fn skips_names_that_cannot_be_linked() {
+        let dir = TempDir::new("unlinked-mentions");
+        std::fs::write(
+            dir.join("Target.md"),
+            "---\naliases: [\"Tgt\", \"T|X\"]\n---\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("C#.md"), "").unwrap();
+        std::fs::write(dir.join("A.md"), "Mentions tgt and C# here.").unwrap();
+        std::fs::write(dir.join("B.md"), "Nothing to see.").unwrap();
+        std::fs::write(dir.join("D.md"), "Only T|X here.").unwrap();
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+
+        let mentions = find_unlinked_mentions_impl(&store, &vault, "Target.md", false).unwrap();
+        let sources: Vec<&str> = mentions.iter().map(|m| m.rel_path.as_str()).collect();
+        assert_eq!(sources, vec!["A.md"]);
+        assert_eq!(mentions[0].occurrences[0].range, 9..12);
+
+        assert!(find_unlinked_mentions_impl(&store, &vault, "C#.md", false)
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            link_mention_impl(&store, &vault, "A.md", 17..19, "C#.md", None),
+            Err(VaultError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,339 @@
+use serde::{Deserialize, Serialize};
+
+/// Give up on finding matches past this edit distance; the differing region is
+/// then treated as one block, which keeps memory bounded for unrelated texts.
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+/// Unit the merge compares and resolves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeGranularity {
+    /// Compare individual lines.
+    #[default]
+    Line,
+    /// Compare blank-line separated blocks, so edits to different lines of the
+    /// same paragraph conflict instead of interleaving.
+    Paragraph,
+}
+
+/// A region of the merged note.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MergeHunk {
+    /// Text both sides agree on, or that only one side changed.
+    Resolved { text: String },
+    /// Both sides changed the same region differently.
+    Conflict {
+        base: String,
+        ours: String,
+        theirs: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeResult {
+    /// Merged text, with conflict markers around unresolved hunks
+    pub merged: String,
+    /// Merged text split into resolved and conflicting regions
+    pub hunks: Vec<MergeHunk>,
+    /// Number of conflicting hunks
+    pub conflicts: usize,
+}
+
+/// Split text into merge units. Concatenating the units yields the input.
+fn tokenize(text: &str, granularity: MergeGranularity) -> Vec<&str> {
+    let lines = text.split_inclusive('\n');
+    if granularity == MergeGranularity::Line {
+        return lines.collect();
+    }
+
+    // A paragraph is a run of non-blank lines plus the blank lines after it.
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+    let mut in_blank_tail = false;
+    for line in lines {
+        let blank = line.trim().is_empty();
+        if !blank && in_blank_tail {
+            blocks.push(&text[start..end]);
+            start = end;
+        }
+        in_blank_tail = blank && end > start;
+        end += line.len();
+    }
+    if end > start {
+        blocks.push(&text[start..end]);
+    }
+    blocks
+}
+
+/// For each token of `a`, the index of the token of `b` it is matched with in
+/// a longest common subsequence (Myers' O(ND) diff).
+fn match_tokens(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; a.len()];
+
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    for (i, m) in matches.iter_mut().enumerate().take(prefix) {
+        *m = Some(i);
+    }
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    for i in 0..suffix {
+        matches[a.len() - 1 - i] = Some(b.len() - 1 - i);
+    }
+
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+    if a_mid.is_empty() || b_mid.is_empty() {
+        return matches;
+    }
+
+    let n = a_mid.len() as isize;
+    let m = b_mid.len() as isize;
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // trace[d] holds v[-d..=d] as it was before step d.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut found = false;
+    for d in 0..=max.min(MAX_EDIT_DISTANCE) as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let idx = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a_mid[x as usize] == b_mid[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                found = true;
+                break;
+            }
+        }
+        if found {
+            break;
+        }
+    }
+    if !found {
+        return matches;
+    }
+
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = if d == 0 { 0 } else { at(prev_k) };
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            matches[prefix + x as usize] = Some(prefix + y as usize);
+        }
+        if d > 0 {
+            x = prev_x;
+            y = prev_y;
+        }
+    }
+    matches
+}
+
+fn push_resolved(hunks: &mut Vec<MergeHunk>, text: String) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(MergeHunk::Resolved { text: last }) = hunks.last_mut() {
+        last.push_str(&text);
+        return;
+    }
+    hunks.push(MergeHunk::Resolved { text });
+}
+
+/// Three-way merge of `ours` and `theirs` against their common `base`.
+pub fn merge3(base: &str, ours: &str, theirs: &str, granularity: MergeGranularity) -> MergeResult {
+    let base_tokens = tokenize(base, granularity);
+    let our_tokens = tokenize(ours, granularity);
+    let their_tokens = tokenize(theirs, granularity);
+    let to_ours = match_tokens(&base_tokens, &our_tokens);
+    let to_theirs = match_tokens(&base_tokens, &their_tokens);
+
+    let mut hunks: Vec<MergeHunk> = Vec::new();
+    let (mut o, mut a, mut b) = (0, 0, 0);
+    loop {
+        // Region where all three agree.
+        let mut stable = 0;
+        while o + stable < base_tokens.len()
+            && to_ours[o + stable] == Some(a + stable)
+            && to_theirs[o + stable] == Some(b + stable)
+        {
+            stable += 1;
+        }
+        if stable > 0 {
+            push_resolved(&mut hunks, base_tokens[o..o + stable].concat());
+            o += stable;
+            a += stable;
+            b += stable;
+            continue;
+        }
+
+        // Next base token both sides kept ends the changed region.
+        let next = (o..base_tokens.len()).find_map(|i| match (to_ours[i], to_theirs[i]) {
+            (Some(j), Some(k)) => Some((i, j, k)),
+            _ => None,
+        });
+        let (o_end, a_end, b_end) =
+            next.unwrap_or((base_tokens.len(), our_tokens.len(), their_tokens.len()));
+        if (o_end, a_end, b_end) == (o, a, b) {
+            break;
+        }
+
+        let base_text = base_tokens[o..o_end].concat();
+        let our_text = our_tokens[a..a_end].concat();
+        let their_text = their_tokens[b..b_end].concat();
+        if our_text == base_text || our_text == their_text {
+            push_resolved(&mut hunks, their_text);
+        } else if their_text == base_text {
+            push_resolved(&mut hunks, our_text);
+        } else {
+            hunks.push(MergeHunk::Conflict {
+                base: base_text,
+                ours: our_text,
+                theirs: their_text,
+            });
+        }
+        (o, a, b) = (o_end, a_end, b_end);
+    }
+
+    let mut merged = String::with_capacity(ours.len().max(theirs.len()));
+    let mut conflicts = 0;
+    for hunk in &hunks {
+        match hunk {
+            MergeHunk::Resolved { text } => merged.push_str(text),
+            MergeHunk::Conflict { ours, theirs, .. } => {
+                conflicts += 1;
+                if !merged.is_empty() && !merged.ends_with('\n') {
+                    merged.push('\n');
+                }
+                merged.push_str("<<<<<<< ours\n");
+                push_block(&mut merged, ours);
+                merged.push_str("=======\n");
+                push_block(&mut merged, theirs);
+                merged.push_str(">>>>>>> theirs\n");
+            }
+        }
+    }
+
+    MergeResult {
+        merged,
+        hunks,
+        conflicts,
+    }
+}
+
+/// Append a conflict side, making sure the following marker starts a line.
+fn push_block(out: &mut String, text: &str) {
+    out.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+pub fn merge_note_versions_impl(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    granularity: Option<MergeGranularity>,
+) -> MergeResult {
+    merge3(base, ours, theirs, granularity.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{match_tokens, merge3, tokenize, MergeGranularity, MergeHunk};
+
+    #[test]
+    fn tokenizes_paragraphs() {
+        let text = "# Title\n\nOne\nTwo\n\n\nThree";
+        assert_eq!(
+            tokenize(text, MergeGranularity::Paragraph),
+            vec!["# Title\n\n", "One\nTwo\n\n\n", "Three"]
+        );
+        assert_eq!(tokenize(text, MergeGranularity::Line).concat(), text);
+    }
+
+    #[test]
+    fn matches_common_subsequence() {
+        let a = ["a", "b", "c", "d"];
+        let b = ["a", "x", "c", "d", "e"];
+        assert_eq!(match_tokens(&a, &b), vec![Some(0), None, Some(2), Some(3)]);
+        assert_eq!(match_tokens(&[], &b), Vec::<Option<usize>>::new());
+    }
+
+    #[test]
+    fn merges_independent_edits() {
+        let base = "one\ntwo\nthree\nfour\n";
+        let ours = "ONE\ntwo\nthree\nfour\n";
+        let theirs = "one\ntwo\nthree\nFOUR\nfive\n";
+        let result = merge3(base, ours, theirs, MergeGranularity::Line);
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.merged, "ONE\ntwo\nthree\nFOUR\nfive\n");
+    }
+
+    #[test]
+    fn identical_edits_do_not_conflict() {
+        let result = merge3("a\nb\n", "a\nB\n", "a\nB\n", MergeGranularity::Line);
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.merged, "a\nB\n");
+    }
+
+    #[test]
+    fn marks_overlapping_edits() {
+        let result = merge3(
+            "a\nb\nc\n",
+            "a\nours\nc\n",
+            "a\ntheirs\nc\n",
+            MergeGranularity::Line,
+        );
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(
+            result.merged,
+            "a\n<<<<<<< ours\nours\n=======\ntheirs\n>>>>>>> theirs\nc\n"
+        );
+        assert!(result.hunks.contains(&MergeHunk::Conflict {
+            base: "b\n".into(),
+            ours: "ours\n".into(),
+            theirs: "theirs\n".into(),
+        }));
+    }
+
+    #[test]
+    fn paragraph_mode_conflicts_within_a_paragraph() {
+        let base = "Intro\n\nline one\nmiddle\nline two\n";
+        let ours = "Intro\n\nLINE ONE\nmiddle\nline two\n";
+        let theirs = "Intro\n\nline one\nmiddle\nLINE TWO\n";
+        assert_eq!(
+            merge3(base, ours, theirs, MergeGranularity::Line).conflicts,
+            0
+        );
+        assert_eq!(
+            merge3(base, ours, theirs, MergeGranularity::Paragraph).conflicts,
+            1
+        );
+    }
+}
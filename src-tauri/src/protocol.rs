@@ -0,0 +1,479 @@
+use crate::error::{VaultError, VaultResult};
+use crate::thumbnails::{read_vault_thumbnail_impl, ThumbnailCache};
+use crate::vault::{mime_for_path, resolve_existing_asset_path, resolve_vault};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Scheme serving vault files to the webview, as
+/// `vault://localhost/<vault path>/<rel path>` with both parts percent-encoded
+/// (`http://vault.localhost/...` on Windows). Only the vault the app has open
+/// is served. An optional `max_dimension` query parameter serves a thumbnail
+/// like `read-vault-image` does.
+pub const VAULT_PROTOCOL: &str = "vault";
+
+/// Largest body sent for an open-ended range such as `bytes=0-`, so seeking
+/// in long videos does not read the whole file into memory.
+const MAX_RANGE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// What `vault://` requests may reach, taken from the app rather than the
+/// request.
+#[derive(Debug, Default)]
+pub struct AssetScope<'a> {
+    /// Canonical path of the open vault; requests for any other root are
+    /// refused
+    pub vault: Option<&'a Path>,
+    /// Origin of the app's own webview, the only one allowed to read
+    /// responses from script
+    pub app_origin: Option<&'a str>,
+}
+
+/// The parts of a protocol request that matter for serving an asset.
+#[derive(Debug, Default)]
+pub struct AssetRequest<'a> {
+    pub method: &'a str,
+    pub origin: Option<&'a str>,
+    /// Still percent-encoded, as in the request URI
+    pub path: &'a str,
+    pub query: Option<&'a str>,
+    pub range: Option<&'a str>,
+    pub if_range: Option<&'a str>,
+    pub if_none_match: Option<&'a str>,
+    pub if_modified_since: Option<&'a str>,
+}
+
+/// A protocol response, converted to the webview's HTTP type by the caller.
+#[derive(Debug)]
+pub struct AssetResponse {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl AssetResponse {
+    fn error(e: &VaultError) -> Self {
+        let status = match e {
+            VaultError::NotFound(_) => 404,
+            VaultError::EscapesVault(_) | VaultError::PermissionDenied(_) => 403,
+            VaultError::Invalid(_) | VaultError::NotMarkdown(_) => 400,
+            _ => 500,
+        };
+        AssetResponse {
+            status,
+            headers: vec![("Content-Type", "text/plain; charset=utf-8".to_string())],
+            body: e.to_string().into_bytes(),
+        }
+    }
+}
+
+pub(crate) fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let hex = text.get(idx + 1..idx + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            idx += 3;
+        } else {
+            decoded.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Split a request path into the vault path and the asset's rel_path.
+fn parse_asset_path(path: &str) -> VaultResult<(String, String)> {
+    let invalid = || VaultError::Invalid("malformed vault asset URL".to_string());
+    let (vault, rel_path) = path
+        .trim_start_matches('/')
+        .split_once('/')
+        .ok_or_else(invalid)?;
+    let vault = percent_decode(vault).ok_or_else(invalid)?;
+    let rel_path = percent_decode(rel_path).ok_or_else(invalid)?;
+    if vault.is_empty() || rel_path.is_empty() {
+        return Err(invalid());
+    }
+    Ok((vault, rel_path))
+}
+
+fn parse_max_dimension(query: Option<&str>) -> VaultResult<Option<u32>> {
+    let Some(value) = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("max_dimension="))
+    else {
+        return Ok(None);
+    };
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| VaultError::Invalid("invalid max_dimension".to_string()))
+}
+
+/// The byte range requested by a `Range` header, for a body of `len` bytes.
+/// `Ok(None)` means the header is absent or not a byte range and the whole
+/// body is sent; `Err` means it cannot be satisfied. Only the first of
+/// several ranges is honoured.
+fn parse_range(header: Option<&str>, len: u64) -> Result<Option<Range<u64>>, ()> {
+    let Some(spec) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+        return Ok(None);
+    };
+    let spec = spec.split(',').next().unwrap_or("").trim();
+    let (start, end) = spec.split_once('-').ok_or(())?;
+    let (start, end) = (start.trim(), end.trim());
+    let range = if start.is_empty() {
+        let suffix: u64 = end.parse().map_err(|_| ())?;
+        if suffix == 0 {
+            return Err(());
+        }
+        len.saturating_sub(suffix)..len
+    } else {
+        let start: u64 = start.parse().map_err(|_| ())?;
+        let end = if end.is_empty() {
+            len.min(start.saturating_add(MAX_RANGE_BYTES))
+        } else {
+            let end: u64 = end.parse().map_err(|_| ())?;
+            if end < start {
+                return Err(());
+            }
+            len.min(end.saturating_add(1))
+        };
+        start..end
+    };
+    if range.start >= len {
+        return Err(());
+    }
+    Ok(Some(range))
+}
+
+/// RFC 7231 date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn http_date(mtime_ms: u64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let secs = mtime_ms / 1000;
+    let days = secs / 86_400;
+    let rem = secs % 86_400;
+
+    // Civil date from days since 1970-01-01, after Howard Hinnant.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{}, {day:02} {} {year} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        MONTHS[(month - 1) as usize],
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+fn read_file_range(path: &std::path::Path, range: &Range<u64>) -> VaultResult<Vec<u8>> {
+    let mut file =
+        std::fs::File::open(path).map_err(|e| VaultError::io(e, "failed to open asset"))?;
+    file.seek(SeekFrom::Start(range.start))
+        .map_err(|e| VaultError::io(e, "failed to seek asset"))?;
+    let mut body = Vec::with_capacity((range.end - range.start) as usize);
+    file.take(range.end - range.start)
+        .read_to_end(&mut body)
+        .map_err(|e| VaultError::io(e, "failed to read asset"))?;
+    Ok(body)
+}
+
+/// The open vault, if the request is for it.
+fn requested_vault(scope: &AssetScope, requested: &str) -> VaultResult<String> {
+    let outside = || VaultError::EscapesVault("asset is outside the open vault".to_string());
+    let vault = scope.vault.ok_or_else(outside)?;
+    if resolve_vault(requested).map_err(|_| outside())? != vault {
+        return Err(outside());
+    }
+    Ok(vault.to_string_lossy().to_string())
+}
+
+fn serve(
+    cache: &ThumbnailCache,
+    scope: &AssetScope,
+    request: &AssetRequest,
+) -> VaultResult<AssetResponse> {
+    let head = match request.method {
+        "GET" => false,
+        "HEAD" => true,
+        _ => {
+            return Ok(AssetResponse {
+                status: 405,
+                headers: vec![("Allow", "GET, HEAD".to_string())],
+                body: Vec::new(),
+            })
+        }
+    };
+    let (vault_path, rel_path) = parse_asset_path(request.path)?;
+    let vault_path = requested_vault(scope, &vault_path)?;
+    let max_dimension = parse_max_dimension(request.query)?;
+    let path = resolve_existing_asset_path(&vault_path, &rel_path)?;
+    let metadata =
+        std::fs::metadata(&path).map_err(|e| VaultError::io(e, "failed to read metadata"))?;
+    let mtime_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |age| age.as_millis() as u64);
+
+    let etag = match max_dimension {
+        Some(max_dimension) => format!("\"{mtime_ms:x}-{:x}-{max_dimension}\"", metadata.len()),
+        None => format!("\"{mtime_ms:x}-{:x}\"", metadata.len()),
+    };
+    let last_modified = http_date(mtime_ms);
+    let mut headers = vec![
+        ("ETag", etag.clone()),
+        ("Last-Modified", last_modified.clone()),
+        // Files change under the app; always revalidate, which is cheap.
+        ("Cache-Control", "no-cache".to_string()),
+        ("Accept-Ranges", "bytes".to_string()),
+        ("Vary", "Origin".to_string()),
+    ];
+    if let Some(origin) = request
+        .origin
+        .filter(|origin| Some(*origin) == scope.app_origin)
+    {
+        headers.push(("Access-Control-Allow-Origin", origin.to_string()));
+        headers.push((
+            "Access-Control-Expose-Headers",
+            "ETag, Last-Modified, Content-Range".to_string(),
+        ));
+    }
+
+    let not_modified = match request.if_none_match {
+        Some(tags) => tags
+            .split(',')
+            .any(|tag| tag.trim() == etag || tag.trim() == "*"),
+        None => request.if_modified_since == Some(last_modified.as_str()),
+    };
+    if not_modified {
+        return Ok(AssetResponse {
+            status: 304,
+            headers,
+            body: Vec::new(),
+        });
+    }
+
+    // Thumbnails are small and built in memory; files are read by range.
+    let (thumbnail, mime, len) = match max_dimension {
+        Some(max_dimension) => {
+            let image = read_vault_thumbnail_impl(cache, &vault_path, &rel_path, max_dimension)?;
+            let len = image.bytes.len() as u64;
+            (Some(image.bytes), image.mime, len)
+        }
+        None => (None, mime_for_path(&path), metadata.len()),
+    };
+    headers.push(("Content-Type", mime));
+
+    let range_header = match request.if_range {
+        Some(validator) if validator != etag && validator != last_modified => None,
+        _ => request.range,
+    };
+    let range = match parse_range(range_header, len) {
+        Ok(range) => range,
+        Err(()) => {
+            headers.push(("Content-Range", format!("bytes */{len}")));
+            return Ok(AssetResponse {
+                status: 416,
+                headers,
+                body: Vec::new(),
+            });
+        }
+    };
+    let status = match &range {
+        Some(range) => {
+            headers.push((
+                "Content-Range",
+                format!("bytes {}-{}/{len}", range.start, range.end - 1),
+            ));
+            206
+        }
+        None => 200,
+    };
+    let range = range.unwrap_or(0..len);
+    headers.push(("Content-Length", (range.end - range.start).to_string()));
+
+    let body = if head {
+        Vec::new()
+    } else {
+        match thumbnail {
+            Some(bytes) => bytes[range.start as usize..range.end as usize].to_vec(),
+            None => read_file_range(&path, &range)?,
+        }
+    };
+    Ok(AssetResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+/// Answer a `vault://` request. Paths get the same boundary checks as
+/// `read-vault-image`, against the open vault in `scope`; failures become
+/// plain-text error responses.
+pub fn serve_vault_asset(
+    cache: &ThumbnailCache,
+    scope: &AssetScope,
+    request: &AssetRequest,
+) -> AssetResponse {
+    serve(cache, scope, request).unwrap_or_else(|e| AssetResponse::error(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        http_date, parse_range, serve_vault_asset, AssetRequest, AssetResponse, AssetScope,
+    };
+    use crate::testing::TempDir;
+    use crate::thumbnails::ThumbnailCache;
+
+    fn header<'a>(response: &'a AssetResponse, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert_eq!(parse_range(None, 10), Ok(None));
+        assert_eq!(parse_range(Some("bytes=2-4"), 10), Ok(Some(2..5)));
+        assert_eq!(parse_range(Some("bytes=5-"), 10), Ok(Some(5..10)));
+        assert_eq!(parse_range(Some("bytes=-3"), 10), Ok(Some(7..10)));
+        assert_eq!(parse_range(Some("bytes=8-20, 0-1"), 10), Ok(Some(8..10)));
+        assert_eq!(parse_range(Some("bytes=10-"), 10), Err(()));
+        assert_eq!(parse_range(Some("bytes=4-2"), 10), Err(()));
+        assert_eq!(parse_range(Some("items=0-1"), 10), Ok(None));
+    }
+
+    #[test]
+    fn formats_http_dates() {
+        assert_eq!(http_date(784_111_777_000), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(http_date(951_782_400_000), "Tue, 29 Feb 2000 00:00:00 GMT");
+    }
+
+    #[test]
+    fn serves_ranges_and_revalidates() {
+        let dir = TempDir::new("vault-protocol");
+        std::fs::create_dir_all(dir.join("media")).unwrap();
+        std::fs::write(dir.join("media/clip a.mp3"), b"0123456789").unwrap();
+        let root = std::fs::canonicalize(&dir).unwrap();
+        let scope = AssetScope {
+            vault: Some(&root),
+            app_origin: Some("tauri://localhost"),
+        };
+        let vault = dir.to_string_lossy().replace('/', "%2F");
+        let path = format!("/{vault}/media%2Fclip%20a.mp3");
+        let cache = ThumbnailCache::new(None);
+
+        let full = serve_vault_asset(
+            &cache,
+            &scope,
+            &AssetRequest {
+                method: "GET",
+                origin: Some("tauri://localhost"),
+                path: &path,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            (full.status, full.body.as_slice()),
+            (200, &b"0123456789"[..])
+        );
+        assert_eq!(header(&full, "Content-Type"), Some("audio/mpeg"));
+        assert_eq!(
+            header(&full, "Access-Control-Allow-Origin"),
+            Some("tauri://localhost")
+        );
+        let etag = header(&full, "ETag").unwrap();
+
+        let partial = serve_vault_asset(
+            &cache,
+            &scope,
+            &AssetRequest {
+                method: "GET",
+                path: &path,
+                range: Some("bytes=2-4"),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            (partial.status, partial.body.as_slice()),
+            (206, &b"234"[..])
+        );
+        assert_eq!(header(&partial, "Content-Range"), Some("bytes 2-4/10"));
+
+        let cached = serve_vault_asset(
+            &cache,
+            &scope,
+            &AssetRequest {
+                method: "GET",
+                path: &path,
+                if_none_match: Some(etag),
+                ..Default::default()
+            },
+        );
+        assert_eq!(cached.status, 304);
+
+        let foreign = serve_vault_asset(
+            &cache,
+            &scope,
+            &AssetRequest {
+                method: "GET",
+                origin: Some("https://example.com"),
+                path: &path,
+                ..Default::default()
+            },
+        );
+        assert_eq!(header(&foreign, "Access-Control-Allow-Origin"), None);
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_open_vault() {
+        let dir = TempDir::new("vault-protocol-scope");
+        std::fs::create_dir_all(dir.join("vault")).unwrap();
+        std::fs::write(dir.join("vault/a.png"), b"png").unwrap();
+        std::fs::write(dir.join("secret.txt"), b"secret").unwrap();
+        let root = std::fs::canonicalize(dir.join("vault")).unwrap();
+        let cache = ThumbnailCache::new(None);
+        let get = |scope: &AssetScope, vault: &std::path::Path, rel_path: &str| {
+            let vault = vault.to_string_lossy().replace('/', "%2F");
+            let path = format!("/{vault}/{}", rel_path.replace('/', "%2F"));
+            serve_vault_asset(
+                &cache,
+                scope,
+                &AssetRequest {
+                    method: "GET",
+                    path: &path,
+                    ..Default::default()
+                },
+            )
+            .status
+        };
+        let scope = AssetScope {
+            vault: Some(&root),
+            ..Default::default()
+        };
+
+        assert_eq!(get(&scope, &root, "a.png"), 200);
+        assert_eq!(get(&scope, &root, "../secret.txt"), 403);
+        // The vault named in the URL must be the open one.
+        assert_eq!(get(&scope, &dir, "secret.txt"), 403);
+        assert_eq!(get(&scope, std::path::Path::new("/"), "etc/passwd"), 403);
+        assert_eq!(get(&AssetScope::default(), &root, "a.png"), 403);
+    }
+}
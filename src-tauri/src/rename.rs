@@ -0,0 +1,541 @@
+use crate::error::{VaultError, VaultResult};
+use crate::index::{rel_path_key, IndexedNote, LinkIndex, LinkIndexStore};
+use crate::resolver::Resolver;
+use crate::trash::is_in_trash;
+use crate::vault::{
+    path_to_rel_string, rename_no_replace, resolve_existing_folder_path,
+    resolve_existing_note_path, resolve_folder_path_for_create, resolve_note_path_for_create,
+    resolve_vault, write_note_impl,
+};
+use crate::wikilinks::{
+    normalize_wikilink_target, parse_markdown_images, parse_markdown_links, parse_wikilinks,
+    percent_encode_path, resolve_destination_path,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::Path;
+
+/// Result of moving a note or folder and rewriting links to it.
+#[derive(Debug, Serialize)]
+pub struct RenameReport {
+    /// Previous rel_path of the note or folder
+    pub from: String,
+    /// New rel_path of the note or folder
+    pub to: String,
+    /// Notes whose links were rewritten to the new target
+    pub updated: Vec<String>,
+    /// Notes that still link to the old target because rewriting failed
+    pub failed: Vec<String>,
+}
+
+impl RenameReport {
+    /// Paths whose notes changed: the old and new location and every note
+    /// whose links were rewritten.
+    pub fn changed_paths(&self) -> Vec<String> {
+        let mut paths = vec![self.from.clone(), self.to.clone()];
+        paths.extend(self.updated.iter().cloned());
+        paths
+    }
+}
+
+/// Drop a trailing Markdown extension, keeping the original casing.
+pub(crate) fn strip_markdown_extension(rel_path: &str) -> &str {
+    let lower = rel_path.to_ascii_lowercase();
+    for ext in [".md", ".markdown"] {
+        if lower.ends_with(ext) {
+            return &rel_path[..rel_path.len() - ext.len()];
+        }
+    }
+    rel_path
+}
+
+/// Keep an explicit extension if the author wrote one.
+fn keep_extension(name: &str, target: &str) -> String {
+    if name.trim().to_ascii_lowercase().ends_with(".md") {
+        format!("{target}.md")
+    } else {
+        target.to_string()
+    }
+}
+
+/// Apply non-overlapping `(range, replacement)` edits to `text`. Returns
+/// `None` when there are none.
+fn apply_edits(text: &str, mut edits: Vec<(Range<usize>, String)>) -> Option<String> {
+    if edits.is_empty() {
+        return None;
+    }
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut out = String::with_capacity(text.len());
+    let mut idx = 0;
+    for (range, replacement) in edits {
+        out.push_str(&text[idx..range.start]);
+        out.push_str(&replacement);
+        idx = range.end;
+    }
+    out.push_str(&text[idx..]);
+    Some(out)
+}
+
+/// Markdown destination reaching `target` from the note at `source`,
+/// relative to the note's folder.
+fn relative_destination(source: &str, target: &str) -> String {
+    let mut from: Vec<&str> = source.split('/').collect();
+    from.pop();
+    let to: Vec<&str> = target.split('/').collect();
+    let common = from
+        .iter()
+        .zip(&to)
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(to.len() - 1);
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(to[common..].iter().map(|part| percent_encode_path(part)));
+    parts.join("/")
+}
+
+/// How to write a link to `rel_path` from the note at `source`: as many
+/// trailing path segments as `written` had, if that reaches the note, or else
+/// the full path. Keeps `[[Note]]` and `[[folder/Note]]` short when possible.
+fn pinned_target(resolver: &Resolver, source: &str, written: &str, rel_path: &str) -> String {
+    let path = strip_markdown_extension(rel_path);
+    let parts: Vec<&str> = path.split('/').collect();
+    let segments = written.split('/').filter(|s| !s.is_empty()).count().max(1);
+    if segments < parts.len() {
+        let suffix = parts[parts.len() - segments..].join("/");
+        if resolver.resolve(source, &rel_path_key(&suffix)) == Some(rel_path) {
+            return suffix;
+        }
+    }
+    path.to_string()
+}
+
+/// Links to rewrite in one note after a move.
+struct NotePlan {
+    /// rel_path of the note before the move
+    old_source: String,
+    /// Normalized wikilink targets mapped to a target reaching the same note
+    wikilinks: HashMap<String, String>,
+    /// Whether Markdown link destinations must be checked too
+    markdown: bool,
+}
+
+/// Links that would break or resolve differently once every note moves to
+/// `moved(rel_path)`, keyed by the linking note's new rel_path. Wikilinks are
+/// compared through the resolver before and after the move; Markdown links
+/// are checked later, in the notes accepted by `check_markdown`.
+fn plan_link_rewrites(
+    index: &LinkIndex,
+    moved: impl Fn(&str) -> String + Copy,
+    check_markdown: impl Fn(&str, &IndexedNote) -> bool,
+) -> BTreeMap<String, NotePlan> {
+    let before = index.resolver();
+    let after = index.moved_resolver(moved);
+    index
+        .notes()
+        .filter_map(|(source, note)| {
+            let new_source = moved(source);
+            let wikilinks: HashMap<String, String> = note
+                .links
+                .iter()
+                // `/`-prefixed targets are Markdown links, handled separately.
+                .filter(|link| !link.starts_with('/'))
+                .filter_map(|link| {
+                    let expected = moved(before.resolve(source, link)?);
+                    (after.resolve(&new_source, link) != Some(expected.as_str())).then(|| {
+                        let target = pinned_target(&after, &new_source, link, &expected);
+                        (link.clone(), target)
+                    })
+                })
+                .collect();
+            let plan = NotePlan {
+                old_source: source.clone(),
+                markdown: check_markdown(source, note),
+                wikilinks,
+            };
+            (plan.markdown || !plan.wikilinks.is_empty()).then_some((new_source, plan))
+        })
+        .collect()
+}
+
+/// Rewrite the links of the note now at `new_source` according to `plan`.
+/// Markdown links and images whose destination no longer reaches the moved
+/// file get a new percent-encoded destination, relative to the note unless
+/// it was written from the vault root.
+fn rewrite_links(
+    text: &str,
+    new_source: &str,
+    plan: &NotePlan,
+    moved: impl Fn(&str) -> String,
+) -> Option<String> {
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    for link in parse_wikilinks(text) {
+        let name = &text[link.target_range.clone()];
+        if let Some(target) = plan.wikilinks.get(&normalize_wikilink_target(name)) {
+            edits.push((link.target_range, keep_extension(name, target)));
+        }
+    }
+
+    let markdown = parse_markdown_links(text)
+        .into_iter()
+        .chain(parse_markdown_images(text))
+        .filter(|_| plan.markdown);
+    for link in markdown {
+        let destination = &link.destination;
+        let Some(old_target) = resolve_destination_path(&plan.old_source, destination) else {
+            continue;
+        };
+        let new_target = moved(&old_target);
+        if resolve_destination_path(new_source, destination).as_deref() == Some(&new_target) {
+            continue;
+        }
+        let suffix = destination
+            .find(['#', '?'])
+            .map_or("", |idx| &destination[idx..]);
+        let path = if destination.starts_with('/') {
+            format!("/{}", percent_encode_path(&new_target))
+        } else {
+            relative_destination(new_source, &new_target)
+        };
+        edits.push((link.destination_range, format!("{path}{suffix}")));
+    }
+
+    apply_edits(text, edits)
+}
+
+/// Move a note. Wikilinks across the vault that would resolve differently
+/// afterwards are rewritten to reach it again, keeping their form where the
+/// new name allows: `[[Old]]` becomes `[[New]]`, `[[folder/Old]]` becomes
+/// `[[folder/New]]`. Markdown links to the note, and the note's own relative
+/// Markdown links, are rewritten as well.
+pub fn rename_note_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    from: &str,
+    to: &str,
+) -> VaultResult<RenameReport> {
+    let source = resolve_existing_note_path(vault_path, from)?;
+    let dest = resolve_note_path_for_create(vault_path, to)?;
+
+    // Allow case-only renames on case-insensitive filesystems.
+    if dest.exists() && std::fs::canonicalize(&dest).ok().as_ref() != Some(&source) {
+        return Err(VaultError::AlreadyExists("note already exists".to_string()));
+    }
+
+    let vault = resolve_vault(vault_path)?;
+    let from_rel = path_to_rel_string(&vault, &source)?;
+    let to_rel = path_to_rel_string(&vault, &dest)?;
+    if is_in_trash(&from_rel) || is_in_trash(&to_rel) {
+        return Err(VaultError::Invalid(
+            "notes cannot be moved into or out of the trash".to_string(),
+        ));
+    }
+
+    let mut report = RenameReport {
+        from: from_rel.clone(),
+        to: to_rel.clone(),
+        updated: Vec::new(),
+        failed: Vec::new(),
+    };
+    if from_rel == to_rel {
+        return Ok(report);
+    }
+
+    // Work out which links point at the note while it is still in place.
+    let moved = |rel_path: &str| {
+        if rel_path == from_rel {
+            to_rel.clone()
+        } else {
+            rel_path.to_string()
+        }
+    };
+    let markdown_key = format!("/{}", rel_path_key(&from_rel));
+    let rewrites = store.with_index(vault_path, |_, index| {
+        plan_link_rewrites(index, moved, |source, note| {
+            source == from_rel || note.links.contains(&markdown_key)
+        })
+    })?;
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| VaultError::io(e, "failed to create note folder"))?;
+    }
+    // Something may have been created at `dest` while links were planned.
+    rename_no_replace(&source, &dest)?;
+
+    for (rel_path, plan) in &rewrites {
+        update_links(vault_path, &vault, rel_path.clone(), &mut report, |text| {
+            rewrite_links(text, rel_path, plan, moved)
+        });
+    }
+    Ok(report)
+}
+
+/// Rewrite the links of the note at `rel_path`, recording the outcome.
+fn update_links(
+    vault_path: &str,
+    vault: &Path,
+    rel_path: String,
+    report: &mut RenameReport,
+    rewrite: impl Fn(&str) -> Option<String>,
+) {
+    let result = std::fs::read_to_string(vault.join(&rel_path))
+        .map_err(|e| VaultError::io(e, "failed to read note"))
+        .and_then(|text| match rewrite(&text) {
+            Some(updated) => write_note_impl(vault_path, &rel_path, &updated, None).map(|_| true),
+            None => Ok(false),
+        });
+
+    match result {
+        Ok(true) => report.updated.push(rel_path),
+        Ok(false) => {}
+        Err(e) => {
+            log::warn!("failed to update links in {rel_path}: {e}");
+            report.failed.push(rel_path);
+        }
+    }
+}
+
+/// Move a folder with everything in it. Wikilinks across the vault that would
+/// resolve differently afterwards, such as `[[old/folder/Note]]`, are
+/// rewritten to reach the moved note again, as are relative Markdown links
+/// into and out of the folder.
+pub fn rename_folder_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    from: &str,
+    to: &str,
+) -> VaultResult<RenameReport> {
+    let source = resolve_existing_folder_path(vault_path, from)?;
+    let dest = resolve_folder_path_for_create(vault_path, to)?;
+
+    // Allow case-only renames on case-insensitive filesystems.
+    if dest.exists() && std::fs::canonicalize(&dest).ok().as_ref() != Some(&source) {
+        return Err(VaultError::AlreadyExists(
+            "a note or folder already exists at this path".to_string(),
+        ));
+    }
+
+    let vault = resolve_vault(vault_path)?;
+    let from_rel = path_to_rel_string(&vault, &source)?;
+    let to_rel = path_to_rel_string(&vault, &dest)?;
+    if is_in_trash(&from_rel) || is_in_trash(&to_rel) {
+        return Err(VaultError::Invalid(
+            "folders cannot be moved into or out of the trash".to_string(),
+        ));
+    }
+    if to_rel.starts_with(&format!("{from_rel}/")) {
+        return Err(VaultError::Invalid(
+            "a folder cannot be moved into itself".to_string(),
+        ));
+    }
+
+    let mut report = RenameReport {
+        from: from_rel.clone(),
+        to: to_rel.clone(),
+        updated: Vec::new(),
+        failed: Vec::new(),
+    };
+    if from_rel == to_rel {
+        return Ok(report);
+    }
+
+    let prefix = format!("{from_rel}/");
+    let moved = |rel_path: &str| match rel_path.strip_prefix(&prefix) {
+        Some(rest) => format!("{to_rel}/{rest}"),
+        None => rel_path.to_string(),
+    };
+
+    // Links to attachments are not indexed, so every note's Markdown links
+    // are checked.
+    let rewrites = store.with_index(vault_path, |_, index| {
+        plan_link_rewrites(index, moved, |_, _| true)
+    })?;
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| VaultError::io(e, "failed to create parent folder"))?;
+    }
+    // Something may have been created at `dest` while links were planned.
+    rename_no_replace(&source, &dest)?;
+
+    for (rel_path, plan) in &rewrites {
+        update_links(vault_path, &vault, rel_path.clone(), &mut report, |text| {
+            rewrite_links(text, rel_path, plan, moved)
+        });
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rename_folder_impl, rename_note_impl, strip_markdown_extension};
+    use crate::error::VaultError;
+    use crate::index::LinkIndexStore;
+    use crate::testing::TempDir;
+
+    fn write_vault(name: &str, files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new(name);
+        for (rel_path, text) in files {
+            let path = dir.join(rel_path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn strips_extension_preserving_case() {
+        assert_eq!(strip_markdown_extension("A/Note.MD"), "A/Note");
+        assert_eq!(strip_markdown_extension("Note.markdown"), "Note");
+        assert_eq!(strip_markdown_extension("Note"), "Note");
+    }
+
+    #[test]
+    fn rewrites_links_by_how_they_resolved() {
+        let dir = write_vault(
+            "rename-note",
+            &[
+                ("a/folder/Old Name.md", ""),
+                ("b/Old Name.md", ""),
+                ("Ref.md", "[[folder/Old Name]] [[a/folder/Old Name|x]]"),
+                (
+                    "a/folder/Near.md",
+                    "[[Old Name]] ![[Old Name.md]] [[old name#Intro|Alias]] `[[Old Name]]` [[Other]]",
+                ),
+                ("b/Far.md", "[[Old Name]] [[folder/Old Name]]"),
+            ],
+        );
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+
+        let report = rename_note_impl(
+            &store,
+            &vault,
+            "a/folder/Old Name.md",
+            "a/folder/New Name.md",
+        )
+        .unwrap();
+        assert_eq!(
+            report.updated,
+            vec!["Ref.md", "a/folder/Near.md", "b/Far.md"]
+        );
+        assert!(report.failed.is_empty());
+        let read = |rel_path: &str| std::fs::read_to_string(dir.join(rel_path)).unwrap();
+        assert_eq!(
+            read("Ref.md"),
+            "[[folder/New Name]] [[a/folder/New Name|x]]"
+        );
+        assert_eq!(
+            read("a/folder/Near.md"),
+            "[[New Name]] ![[New Name.md]] [[New Name#Intro|Alias]] `[[Old Name]]` [[Other]]"
+        );
+        // The bare link meant the note next to it and is left alone.
+        assert_eq!(read("b/Far.md"), "[[Old Name]] [[folder/New Name]]");
+
+        store.notes_changed(&vault, &report.changed_paths());
+        let report =
+            rename_note_impl(&store, &vault, "a/folder/New Name.md", "archive/Renamed.md").unwrap();
+        assert_eq!(report.updated.len(), 3);
+        assert_eq!(read("Ref.md"), "[[archive/Renamed]] [[archive/Renamed|x]]");
+        assert!(read("a/folder/Near.md").starts_with("[[Renamed]] ![[Renamed.md]]"));
+    }
+
+    #[test]
+    fn keeps_note_moves_inside_the_vault() {
+        let dir = write_vault(
+            "rename-escape",
+            &[("vault/A.md", ""), ("outside/.keep", "")],
+        );
+        let vault = dir.join("vault").to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+
+        let err = rename_note_impl(&store, &vault, "A.md", ".trash/A.md").unwrap_err();
+        assert!(matches!(err, VaultError::Invalid(_)));
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("outside"), dir.join("vault/link")).unwrap();
+            let err = rename_note_impl(&store, &vault, "A.md", "link/new/A.md").unwrap_err();
+            assert!(matches!(err, VaultError::EscapesVault(_)));
+            assert!(!dir.join("outside/new").exists());
+        }
+        assert!(dir.join("vault/A.md").is_file());
+    }
+
+    #[test]
+    fn rewrites_relative_markdown_links() {
+        let dir = write_vault(
+            "rename-markdown",
+            &[
+                ("Notes.md", ""),
+                (
+                    "a/folder/Old Name.md",
+                    "[up](../../Notes.md) [here](./Sibling.md)",
+                ),
+                ("a/folder/Sibling.md", ""),
+                (
+                    "projects/alpha/Plan.md",
+                    "[notes](../../Notes.md) ![](img.png)",
+                ),
+                ("projects/alpha/img.png", ""),
+                (
+                    "Ref.md",
+                    "[see](a/folder/Old%20Name.md#Intro) [root](</a/folder/Old Name.md>) \
+                     [plan](projects/alpha/Plan.md) ![shot](projects/alpha/img.png)",
+                ),
+            ],
+        );
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+        let read = |rel_path: &str| std::fs::read_to_string(dir.join(rel_path)).unwrap();
+
+        let report =
+            rename_note_impl(&store, &vault, "a/folder/Old Name.md", "b/New Name.md").unwrap();
+        assert_eq!(report.updated, vec!["Ref.md", "b/New Name.md"]);
+        assert_eq!(
+            read("b/New Name.md"),
+            "[up](../Notes.md) [here](../a/folder/Sibling.md)"
+        );
+        assert!(
+            read("Ref.md").starts_with("[see](b/New%20Name.md#Intro) [root](</b/New%20Name.md>) ")
+        );
+
+        store.notes_changed(&vault, &report.changed_paths());
+        let report = rename_folder_impl(&store, &vault, "projects/alpha", "alpha").unwrap();
+        assert_eq!(report.updated, vec!["Ref.md", "alpha/Plan.md"]);
+        assert_eq!(read("alpha/Plan.md"), "[notes](../Notes.md) ![](img.png)");
+        assert!(read("Ref.md").ends_with("[plan](alpha/Plan.md) ![shot](alpha/img.png)"));
+    }
+
+    #[test]
+    fn moves_folders_and_pins_changed_links() {
+        let dir = TempDir::new("rename-folder");
+        std::fs::create_dir_all(dir.join("projects/alpha")).unwrap();
+        let files = [
+            ("Notes.md", ""),
+            ("projects/alpha/Notes.md", ""),
+            ("projects/alpha/Plan.md", "[[Notes]]"),
+            (
+                "Index.md",
+                "[[projects/alpha/Plan]] [[Plan]] [[projects/alpha/Notes.md|n]] [[alpha/Notes]]",
+            ),
+        ];
+        for (rel_path, text) in files {
+            std::fs::write(dir.join(rel_path), text).unwrap();
+        }
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+
+        let err = rename_folder_impl(&store, &vault, "projects", "projects/inner");
+        assert!(err.is_err());
+
+        let report = rename_folder_impl(&store, &vault, "projects/alpha", "archive/alpha").unwrap();
+        assert_eq!(report.to, "archive/alpha");
+        assert_eq!(report.updated, vec!["Index.md"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("Index.md")).unwrap(),
+            "[[archive/alpha/Plan]] [[Plan]] [[archive/alpha/Notes.md|n]] [[alpha/Notes]]"
+        );
+        assert!(dir.join("archive/alpha/Plan.md").is_file());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::error::VaultResult;
+use crate::graph::is_hidden_path;
+use crate::index::{rel_path_key, LinkIndexStore};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A link with more than one candidate note.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AmbiguousLink {
+    /// Note containing the link
+    pub source: String,
+    /// Normalized link target
+    pub target: String,
+    /// Every note the target could mean, in rel_path order
+    pub candidates: Vec<String>,
+    /// Candidate the link resolves to
+    pub resolved_to: String,
+}
+
+/// A note as seen by the resolver.
+struct Candidate {
+    rel_path: String,
+    /// Normalized path without extension, e.g. `folder/note`
+    key: String,
+    /// Normalized folder, empty at the vault root
+    dir: String,
+}
+
+/// Resolves normalized link targets to notes.
+///
+/// A target matches notes by file name or path suffix (`[[Note]]`,
+/// `[[folder/Note]]`); `/`-prefixed targets from Markdown links match full
+/// paths only. Frontmatter aliases are consulted only when no note matches,
+/// so an alias never shadows a real note name. When several notes match, the
+/// winner is, in order: an exact path match, a note in the linking note's
+/// folder, a note in its nearest ancestor folder, then the shortest path.
+pub struct Resolver {
+    candidates: Vec<Candidate>,
+    /// Normalized file stem to candidate indexes
+    stems: HashMap<String, Vec<usize>>,
+    /// Normalized path to candidate index
+    paths: HashMap<String, usize>,
+    /// Normalized alias to candidate indexes
+    aliases: HashMap<String, Vec<usize>>,
+}
+
+fn parent_dir(key: &str) -> &str {
+    key.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+impl Resolver {
+    /// Build a resolver from note rel_paths and `(rel_path, normalized alias)`
+    /// pairs.
+    pub fn new<'a>(
+        rel_paths: impl Iterator<Item = &'a String>,
+        aliases: impl Iterator<Item = (&'a String, String)>,
+    ) -> Self {
+        let mut resolver = Resolver {
+            candidates: Vec::new(),
+            stems: HashMap::new(),
+            paths: HashMap::new(),
+            aliases: HashMap::new(),
+        };
+        for rel_path in rel_paths {
+            let idx = resolver.candidates.len();
+            let key = rel_path_key(rel_path);
+            let stem = key.rsplit('/').next().unwrap_or(&key).to_string();
+            resolver.stems.entry(stem).or_default().push(idx);
+            resolver.paths.insert(key.clone(), idx);
+            resolver.candidates.push(Candidate {
+                rel_path: rel_path.clone(),
+                dir: parent_dir(&key).to_string(),
+                key,
+            });
+        }
+        for (rel_path, alias) in aliases {
+            if let Some(&idx) = resolver.paths.get(&rel_path_key(rel_path)) {
+                let owners = resolver.aliases.entry(alias).or_default();
+                if !owners.contains(&idx) {
+                    owners.push(idx);
+                }
+            }
+        }
+        resolver
+    }
+
+    fn candidate_indexes(&self, target: &str) -> Vec<usize> {
+        if let Some(path) = target.strip_prefix('/') {
+            return self.paths.get(path).copied().into_iter().collect();
+        }
+
+        let stem = target.rsplit('/').next().unwrap_or(target);
+        let suffix = format!("/{target}");
+        let by_name: Vec<usize> = self
+            .stems
+            .get(stem)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|&idx| {
+                let key = &self.candidates[idx].key;
+                key == target || key.ends_with(&suffix)
+            })
+            .collect();
+        if !by_name.is_empty() {
+            return by_name;
+        }
+        self.aliases.get(target).cloned().unwrap_or_default()
+    }
+
+    /// Every note `target` could mean, in rel_path order.
+    pub fn candidates(&self, target: &str) -> Vec<&str> {
+        let mut candidates: Vec<&str> = self
+            .candidate_indexes(target)
+            .into_iter()
+            .map(|idx| self.candidates[idx].rel_path.as_str())
+            .collect();
+        candidates.sort_unstable();
+        candidates
+    }
+
+    /// Note that `target`, linked from the note at `source`, resolves to.
+    /// Pass an empty `source` to resolve from the vault root.
+    pub fn resolve(&self, source: &str, target: &str) -> Option<&str> {
+        let indexes = self.candidate_indexes(target);
+        let source_dir = parent_dir(source).to_ascii_lowercase();
+        let candidates = indexes.iter().map(|&idx| &self.candidates[idx]);
+
+        let best = candidates
+            .clone()
+            .find(|c| c.key == target.trim_start_matches('/'))
+            .or_else(|| candidates.clone().find(|c| c.dir == source_dir))
+            .or_else(|| {
+                candidates
+                    .clone()
+                    .filter(|c| {
+                        c.dir.is_empty()
+                            || source_dir
+                                .strip_prefix(c.dir.as_str())
+                                .is_some_and(|rest| rest.starts_with('/'))
+                    })
+                    .max_by_key(|c| c.dir.len())
+            })
+            .or_else(|| {
+                candidates.min_by(|a, b| {
+                    let depth = |c: &Candidate| c.key.matches('/').count();
+                    depth(a)
+                        .cmp(&depth(b))
+                        .then(a.key.len().cmp(&b.key.len()))
+                        .then(a.rel_path.cmp(&b.rel_path))
+                })
+            })?;
+        Some(best.rel_path.as_str())
+    }
+}
+
+/// Every link in the vault with more than one candidate note, grouped by
+/// linking note in rel_path order.
+pub fn list_ambiguous_links_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    show_hidden: bool,
+) -> VaultResult<Vec<AmbiguousLink>> {
+    store.with_index(vault_path, |_, index| {
+        let resolver = index.resolver();
+        let mut ambiguous = Vec::new();
+        for (source, note) in index.notes() {
+            if !show_hidden && is_hidden_path(source) {
+                continue;
+            }
+            for target in &note.links {
+                let candidates = resolver.candidates(target);
+                if candidates.len() < 2 {
+                    continue;
+                }
+                let Some(resolved_to) = resolver.resolve(source, target) else {
+                    continue;
+                };
+                ambiguous.push(AmbiguousLink {
+                    source: source.clone(),
+                    target: target.clone(),
+                    resolved_to: resolved_to.to_string(),
+                    candidates: candidates.into_iter().map(str::to_string).collect(),
+                });
+            }
+        }
+        ambiguous
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Resolver;
+
+    fn resolver(rel_paths: &[&str], aliases: &[(&str, &str)]) -> Resolver {
+        let rel_paths: Vec<String> = rel_paths.iter().map(|p| p.to_string()).collect();
+        let aliases: Vec<(String, String)> = aliases
+            .iter()
+            .map(|(p, a)| (p.to_string(), a.to_string()))
+            .collect();
+        Resolver::new(
+            rel_paths.iter(),
+            aliases.iter().map(|(p, a)| (p, a.clone())),
+        )
+    }
+
+    #[test]
+    fn prefers_exact_then_nearby_then_shortest() {
+        let r = resolver(
+            &[
+                "a/README.md",
+                "a/b/README.md",
+                "a/b/c/README.md",
+                "x/y/README.md",
+                "z/README.md",
+                "Notes.md",
+                "deep/Notes.md",
+            ],
+            &[],
+        );
+        // Same folder, then nearest ancestor.
+        assert_eq!(r.resolve("a/b/Note.md", "readme"), Some("a/b/README.md"));
+        assert_eq!(r.resolve("a/b/q/Note.md", "readme"), Some("a/b/README.md"));
+        // No folder relation: shortest path, ties broken by name.
+        assert_eq!(r.resolve("q/Note.md", "readme"), Some("a/README.md"));
+        // An exact path match beats proximity.
+        assert_eq!(r.resolve("deep/Other.md", "notes"), Some("Notes.md"));
+        assert_eq!(r.resolve("q/Note.md", "y/readme"), Some("x/y/README.md"));
+        assert_eq!(r.resolve("", "/deep/notes"), Some("deep/Notes.md"));
+        assert_eq!(r.resolve("", "/readme"), None);
+
+        assert_eq!(r.candidates("readme").len(), 5);
+        assert_eq!(r.candidates("b/readme"), vec!["a/b/README.md"]);
+    }
+
+    #[test]
+    fn aliases_only_apply_without_a_note_match() {
+        let r = resolver(
+            &["JavaScript.md", "Script.md"],
+            &[("JavaScript.md", "js"), ("JavaScript.md", "script")],
+        );
+        assert_eq!(r.resolve("", "js"), Some("JavaScript.md"));
+        assert_eq!(r.resolve("", "script"), Some("Script.md"));
+        assert_eq!(r.resolve("", "missing"), None);
+    }
+}
@@ -0,0 +1,911 @@
+use crate::error::{VaultError, VaultResult};
+use crate::graph::is_hidden_path;
+use crate::index::file_stamp;
+use crate::vault::{list_markdown_files_impl, resolve_vault};
+use crate::walk::notes_at;
+use crate::watcher::VaultChanges;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// BM25 term frequency saturation.
+const BM25_K1: f64 = 1.2;
+/// BM25 document length normalization.
+const BM25_B: f64 = 0.75;
+const DEFAULT_LIMIT: usize = 50;
+const SNIPPETS_PER_HIT: usize = 3;
+/// Longest snippet, in characters, before the line is cut around the match.
+const SNIPPET_CHARS: usize = 160;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchOptions {
+    /// Maximum number of notes returned (default 50)
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Whether to search notes in hidden files/folders
+    #[serde(default)]
+    pub show_hidden: bool,
+    /// Re-scan the whole vault first, for changes the watcher missed
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// Byte range of a matched term within a snippet.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHighlight {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchSnippet {
+    /// 1-based line number of the snippet in the note
+    pub line: usize,
+    pub text: String,
+    pub highlights: Vec<SearchHighlight>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub rel_path: String,
+    pub score: f64,
+    pub snippets: Vec<SearchSnippet>,
+}
+
+/// Lowercased word tokens of `text` with their byte ranges.
+fn tokenize(text: &str) -> Vec<(String, usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (idx, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        let is_word = c.is_alphanumeric() || c == '_';
+        match (start, is_word) {
+            (None, true) => start = Some(idx),
+            (Some(s), false) => {
+                tokens.push((text[s..idx].to_lowercase(), s, idx));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// A single query term.
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Word(String),
+    Prefix(String),
+    Phrase(Vec<String>),
+}
+
+impl Term {
+    /// Build a term from raw query text; text spanning several tokens, such
+    /// as `foo-bar`, becomes a phrase.
+    fn parse(raw: &str, quoted: bool) -> Option<Term> {
+        let prefix = !quoted && raw.ends_with('*');
+        let mut words: Vec<String> = tokenize(raw).into_iter().map(|(w, _, _)| w).collect();
+        match words.len() {
+            0 => None,
+            1 if prefix => words.pop().map(Term::Prefix),
+            1 if !quoted => words.pop().map(Term::Word),
+            _ => Some(Term::Phrase(words)),
+        }
+    }
+
+    /// Whether a document token counts as a match for highlighting.
+    fn matches_token(&self, token: &str) -> bool {
+        match self {
+            Term::Word(word) => word == token,
+            Term::Prefix(prefix) => token.starts_with(prefix.as_str()),
+            Term::Phrase(words) => words.iter().any(|w| w == token),
+        }
+    }
+}
+
+/// Parsed search query. Clauses are AND-ed; each clause matches if any of
+/// its alternatives (joined with `OR`) does.
+#[derive(Debug, Default, PartialEq)]
+struct Query {
+    clauses: Vec<Vec<Term>>,
+    excluded: Vec<Term>,
+    /// `path:` filters, matched as case-insensitive substrings of rel_path
+    paths: Vec<String>,
+    /// `folder:` filters, matched as rel_path prefixes
+    folders: Vec<String>,
+    /// Negated `path:` and `folder:` filters
+    excluded_paths: Vec<String>,
+    excluded_folders: Vec<String>,
+}
+
+impl Query {
+    /// Parse `foo "exact phrase" pre* a OR b NOT c -d path:x folder:y`.
+    fn parse(input: &str) -> Query {
+        let mut query = Query::default();
+        let mut negate = false;
+        let mut join_or = false;
+
+        for part in split_query(input) {
+            if !part.quoted && !part.negated {
+                match part.text.as_str() {
+                    "AND" => continue,
+                    "OR" => {
+                        join_or = !query.clauses.is_empty() && !negate;
+                        continue;
+                    }
+                    "NOT" => {
+                        negate = true;
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            let negated = part.negated || std::mem::take(&mut negate);
+            let or = std::mem::take(&mut join_or);
+
+            if let Some(path) = part.text.strip_prefix("path:") {
+                if !path.is_empty() {
+                    let filters = if negated {
+                        &mut query.excluded_paths
+                    } else {
+                        &mut query.paths
+                    };
+                    filters.push(path.to_lowercase());
+                }
+                continue;
+            }
+            if let Some(folder) = part.text.strip_prefix("folder:") {
+                let folder = folder.trim_matches('/');
+                if !folder.is_empty() {
+                    let filters = if negated {
+                        &mut query.excluded_folders
+                    } else {
+                        &mut query.folders
+                    };
+                    filters.push(format!("{}/", folder.to_lowercase()));
+                }
+                continue;
+            }
+
+            let Some(term) = Term::parse(&part.text, part.quoted) else {
+                continue;
+            };
+            if negated {
+                query.excluded.push(term);
+            } else if or {
+                if let Some(last) = query.clauses.last_mut() {
+                    last.push(term);
+                }
+            } else {
+                query.clauses.push(vec![term]);
+            }
+        }
+        query
+    }
+
+    /// Whether the query has neither terms nor filters. A query with only
+    /// exclusions or filters still lists every note they accept.
+    fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+            && self.excluded.is_empty()
+            && self.paths.is_empty()
+            && self.folders.is_empty()
+            && self.excluded_paths.is_empty()
+            && self.excluded_folders.is_empty()
+    }
+
+    fn accepts_path(&self, rel_path: &str) -> bool {
+        let lower = rel_path.to_lowercase();
+        self.paths.iter().all(|p| lower.contains(p.as_str()))
+            && self.folders.iter().all(|f| lower.starts_with(f.as_str()))
+            && !self
+                .excluded_paths
+                .iter()
+                .any(|p| lower.contains(p.as_str()))
+            && !self
+                .excluded_folders
+                .iter()
+                .any(|f| lower.starts_with(f.as_str()))
+    }
+
+    fn positive_terms(&self) -> impl Iterator<Item = &Term> {
+        self.clauses.iter().flatten()
+    }
+}
+
+/// One whitespace-separated part of a query.
+#[derive(Debug, Default)]
+struct QueryPart {
+    text: String,
+    /// Whether the text (other than a `field:` prefix) was quoted
+    quoted: bool,
+    /// Whether the part started with `-`
+    negated: bool,
+}
+
+/// Split a query into parts, keeping quoted text together. A quote may follow
+/// a `-` or `field:` prefix, as in `-"old phrase"` or `path:"My Folder"`.
+fn split_query(input: &str) -> Vec<QueryPart> {
+    let mut parts = Vec::new();
+    let mut current = QueryPart::default();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.quoted = true;
+            }
+            '-' if !in_quotes && current.text.is_empty() && !current.quoted => {
+                current.negated = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.text.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+                current = QueryPart::default();
+            }
+            c => current.text.push(c),
+        }
+    }
+    if !current.text.is_empty() {
+        parts.push(current);
+    }
+
+    for part in &mut parts {
+        if part.text.starts_with("path:") || part.text.starts_with("folder:") {
+            part.quoted = false;
+        }
+    }
+    parts
+}
+
+/// Indexed state of one note.
+#[derive(Debug)]
+struct SearchDoc {
+    rel_path: String,
+    mtime_ms: u64,
+    size: u64,
+    /// Number of tokens in the note
+    len: u32,
+    /// Distinct terms, so the note can be removed from the postings
+    terms: Vec<String>,
+}
+
+/// Positional inverted index over the notes of one vault.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    docs: Vec<Option<SearchDoc>>,
+    ids: HashMap<String, u32>,
+    free: Vec<u32>,
+    /// term -> note id -> token positions
+    postings: BTreeMap<String, HashMap<u32, Vec<u32>>>,
+    total_len: u64,
+}
+
+impl SearchIndex {
+    /// Bring the index in line with the vault on disk, re-reading only notes
+    /// whose mtime or size changed.
+    pub fn refresh(&mut self, vault: &Path) -> VaultResult<()> {
+        let files = list_markdown_files_impl(&vault.to_string_lossy())?;
+        let seen: HashSet<&str> = files.iter().map(|f| f.rel_path.as_str()).collect();
+
+        let removed: Vec<String> = self
+            .ids
+            .keys()
+            .filter(|rel_path| !seen.contains(rel_path.as_str()))
+            .cloned()
+            .collect();
+        for rel_path in removed {
+            self.remove(&rel_path);
+        }
+        for file in &files {
+            self.update_note(vault, &file.rel_path);
+        }
+        Ok(())
+    }
+
+    /// Re-read the notes now at or below each of `rel_paths`, dropping those
+    /// that are gone, even when their size and mtime look unchanged.
+    pub fn replace_notes(&mut self, vault: &Path, rel_paths: &[String]) {
+        for rel_path in rel_paths {
+            let prefix = format!("{rel_path}/");
+            let stale: Vec<String> = self
+                .ids
+                .keys()
+                .filter(|note| *note == rel_path || note.starts_with(&prefix))
+                .cloned()
+                .collect();
+            for note in stale {
+                self.remove(&note);
+            }
+            for note in notes_at(vault, rel_path) {
+                self.update_note(vault, &note);
+            }
+        }
+    }
+
+    /// Re-index a single note if it changed on disk, or drop it if it no
+    /// longer exists.
+    pub fn update_note(&mut self, vault: &Path, rel_path: &str) {
+        let path = vault.join(rel_path);
+        let Some((mtime_ms, size, _)) = file_stamp(&path) else {
+            self.remove(rel_path);
+            return;
+        };
+        if let Some(doc) = self.doc(rel_path) {
+            if doc.mtime_ms == mtime_ms && doc.size == size {
+                return;
+            }
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(text) => self.insert(rel_path, mtime_ms, size, &text),
+            Err(_) => self.remove(rel_path),
+        }
+    }
+
+    fn doc(&self, rel_path: &str) -> Option<&SearchDoc> {
+        let id = *self.ids.get(rel_path)?;
+        self.docs[id as usize].as_ref()
+    }
+
+    fn insert(&mut self, rel_path: &str, mtime_ms: u64, size: u64, text: &str) {
+        self.remove(rel_path);
+
+        let id = match self.free.pop() {
+            Some(id) => id,
+            None => {
+                self.docs.push(None);
+                (self.docs.len() - 1) as u32
+            }
+        };
+
+        let tokens = tokenize(text);
+        let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+        for (pos, (token, _, _)) in tokens.into_iter().enumerate() {
+            positions.entry(token).or_default().push(pos as u32);
+        }
+        let len = positions.values().map(|p| p.len() as u32).sum();
+        let terms: Vec<String> = positions.keys().cloned().collect();
+        for (term, positions) in positions {
+            self.postings.entry(term).or_default().insert(id, positions);
+        }
+
+        self.total_len += u64::from(len);
+        self.ids.insert(rel_path.to_string(), id);
+        self.docs[id as usize] = Some(SearchDoc {
+            rel_path: rel_path.to_string(),
+            mtime_ms,
+            size,
+            len,
+            terms,
+        });
+    }
+
+    fn remove(&mut self, rel_path: &str) {
+        let Some(id) = self.ids.remove(rel_path) else {
+            return;
+        };
+        let Some(doc) = self.docs[id as usize].take() else {
+            return;
+        };
+        for term in &doc.terms {
+            if let Some(docs) = self.postings.get_mut(term) {
+                docs.remove(&id);
+                if docs.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        self.total_len -= u64::from(doc.len);
+        self.free.push(id);
+    }
+
+    /// Notes matching `term`, with the term frequency in each.
+    fn term_frequencies(&self, term: &Term) -> Vec<(String, HashMap<u32, u32>)> {
+        match term {
+            Term::Word(word) => self
+                .postings
+                .get(word)
+                .map(|docs| vec![(word.clone(), frequencies(docs))])
+                .unwrap_or_default(),
+            Term::Prefix(prefix) => self
+                .postings
+                .range(prefix.clone()..)
+                .take_while(|(term, _)| term.starts_with(prefix.as_str()))
+                .map(|(term, docs)| (term.clone(), frequencies(docs)))
+                .collect(),
+            Term::Phrase(words) => {
+                let matches = self.phrase_matches(words);
+                if matches.is_empty() {
+                    return Vec::new();
+                }
+                words.iter().map(|w| (w.clone(), matches.clone())).collect()
+            }
+        }
+    }
+
+    /// Notes containing `words` consecutively, with the occurrence count.
+    fn phrase_matches(&self, words: &[String]) -> HashMap<u32, u32> {
+        let lists: Option<Vec<&HashMap<u32, Vec<u32>>>> =
+            words.iter().map(|w| self.postings.get(w)).collect();
+        let Some(lists) = lists else {
+            return HashMap::new();
+        };
+
+        let mut matches = HashMap::new();
+        for (&id, starts) in lists[0] {
+            let count = starts
+                .iter()
+                .filter(|&&start| {
+                    lists[1..].iter().enumerate().all(|(offset, docs)| {
+                        docs.get(&id).is_some_and(|positions| {
+                            positions
+                                .binary_search(&(start + offset as u32 + 1))
+                                .is_ok()
+                        })
+                    })
+                })
+                .count() as u32;
+            if count > 0 {
+                matches.insert(id, count);
+            }
+        }
+        matches
+    }
+
+    fn matching_ids(&self, term: &Term) -> HashSet<u32> {
+        self.term_frequencies(term)
+            .into_iter()
+            .flat_map(|(_, docs)| docs.into_keys())
+            .collect()
+    }
+
+    /// Rank notes matching the query by BM25, best first.
+    fn rank(&self, query: &Query, show_hidden: bool) -> Vec<(String, f64)> {
+        let live = self.ids.len() as f64;
+        if live == 0.0 {
+            return Vec::new();
+        }
+        let avg_len = (self.total_len as f64 / live).max(1.0);
+
+        let mut candidates: Option<HashSet<u32>> = None;
+        for clause in &query.clauses {
+            let ids: HashSet<u32> = clause.iter().flat_map(|t| self.matching_ids(t)).collect();
+            candidates = Some(match candidates {
+                Some(current) => current.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+        let mut candidates =
+            candidates.unwrap_or_else(|| self.ids.values().copied().collect::<HashSet<u32>>());
+        for term in &query.excluded {
+            for id in self.matching_ids(term) {
+                candidates.remove(&id);
+            }
+        }
+        candidates.retain(|&id| {
+            self.docs[id as usize].as_ref().is_some_and(|doc| {
+                (show_hidden || !is_hidden_path(&doc.rel_path)) && query.accepts_path(&doc.rel_path)
+            })
+        });
+
+        let mut scores: HashMap<u32, f64> = candidates.iter().map(|&id| (id, 0.0)).collect();
+        for term in query.positive_terms() {
+            for (word, docs) in self.term_frequencies(term) {
+                let df = self.postings.get(&word).map_or(0, |d| d.len()) as f64;
+                let idf = (1.0 + (live - df + 0.5) / (df + 0.5)).ln();
+                for (id, tf) in docs {
+                    let (Some(score), Some(doc)) =
+                        (scores.get_mut(&id), self.docs[id as usize].as_ref())
+                    else {
+                        continue;
+                    };
+                    let tf = f64::from(tf);
+                    let norm = 1.0 - BM25_B + BM25_B * f64::from(doc.len) / avg_len;
+                    *score += idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * norm);
+                }
+            }
+        }
+
+        let mut ranked: Vec<(String, f64)> = scores
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let doc = self.docs[id as usize].as_ref()?;
+                Some((doc.rel_path.clone(), score))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+}
+
+fn frequencies(docs: &HashMap<u32, Vec<u32>>) -> HashMap<u32, u32> {
+    docs.iter()
+        .map(|(&id, positions)| (id, positions.len() as u32))
+        .collect()
+}
+
+/// Lines of `text` containing query terms, cut down around the first match.
+fn build_snippets(text: &str, terms: &[&Term]) -> Vec<SearchSnippet> {
+    let mut snippets = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let matches: Vec<(usize, usize)> = tokenize(line)
+            .into_iter()
+            .filter(|(token, _, _)| terms.iter().any(|t| t.matches_token(token)))
+            .map(|(_, start, end)| (start, end))
+            .collect();
+        let Some(&(first, _)) = matches.first() else {
+            continue;
+        };
+
+        let (from, to) = snippet_window(line, first);
+        let slice = &line[from..to];
+        let trimmed = slice.trim_start();
+        let offset = from + (slice.len() - trimmed.len());
+        let trimmed = trimmed.trim_end();
+        snippets.push(SearchSnippet {
+            line: idx + 1,
+            text: trimmed.to_string(),
+            highlights: matches
+                .into_iter()
+                .filter(|&(start, end)| start >= offset && end <= offset + trimmed.len())
+                .map(|(start, end)| SearchHighlight {
+                    start: start - offset,
+                    end: end - offset,
+                })
+                .collect(),
+        });
+        if snippets.len() == SNIPPETS_PER_HIT {
+            break;
+        }
+    }
+    snippets
+}
+
+/// Byte range of at most `SNIPPET_CHARS` characters of `line` that starts a
+/// little before `focus`.
+pub(crate) fn snippet_window(line: &str, focus: usize) -> (usize, usize) {
+    let boundaries: Vec<usize> = line
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([line.len()])
+        .collect();
+    let chars = boundaries.len() - 1;
+    if chars <= SNIPPET_CHARS {
+        return (0, line.len());
+    }
+    let focus_char = boundaries.partition_point(|&b| b < focus);
+    let start = focus_char
+        .saturating_sub(SNIPPET_CHARS / 4)
+        .min(chars - SNIPPET_CHARS);
+    (boundaries[start], boundaries[start + SNIPPET_CHARS])
+}
+
+/// In-memory search indexes for every vault opened in this session.
+#[derive(Default)]
+pub struct SearchIndexStore {
+    indexes: Mutex<HashMap<PathBuf, SearchIndex>>,
+}
+
+impl SearchIndexStore {
+    /// Run `f` against the index for the vault, building it on first use.
+    /// Afterwards the watcher and `notes_changed` keep it current, so the
+    /// vault is only scanned again when `refresh` asks for it.
+    pub fn with_index<T>(
+        &self,
+        vault_path: &str,
+        refresh: bool,
+        f: impl FnOnce(&Path, &SearchIndex) -> T,
+    ) -> VaultResult<T> {
+        let vault = resolve_vault(vault_path)?;
+
+        let mut indexes = self
+            .indexes
+            .lock()
+            .map_err(|_| VaultError::other("search index is unavailable"))?;
+        let index = match indexes.entry(vault.clone()) {
+            Entry::Occupied(entry) => {
+                let index = entry.into_mut();
+                if refresh {
+                    index.refresh(&vault)?;
+                }
+                index
+            }
+            Entry::Vacant(entry) => {
+                let mut index = SearchIndex::default();
+                index.refresh(&vault)?;
+                entry.insert(index)
+            }
+        };
+        Ok(f(&vault, index))
+    }
+
+    /// Bring an already loaded index in line with the whole vault, for when
+    /// changes may have been missed, e.g. while the vault was not watched.
+    pub fn refresh(&self, vault_path: &str) {
+        let Ok(vault) = resolve_vault(vault_path) else {
+            return;
+        };
+        let Ok(mut indexes) = self.indexes.lock() else {
+            return;
+        };
+        if let Some(index) = indexes.get_mut(&vault) {
+            if let Err(e) = index.refresh(&vault) {
+                log::warn!("{e}");
+            }
+        }
+    }
+
+    /// Re-index notes the app just created, wrote, moved or deleted, given by
+    /// their rel_paths or those of their folders.
+    pub fn notes_changed(&self, vault_path: &str, rel_paths: &[String]) {
+        let Ok(vault) = resolve_vault(vault_path) else {
+            return;
+        };
+        let Ok(mut indexes) = self.indexes.lock() else {
+            return;
+        };
+        if let Some(index) = indexes.get_mut(&vault) {
+            index.replace_notes(&vault, rel_paths);
+        }
+    }
+
+    /// Apply a batch of watcher changes to an already loaded index.
+    pub fn apply_changes(&self, vault: &Path, changes: &VaultChanges) {
+        let Ok(mut indexes) = self.indexes.lock() else {
+            return;
+        };
+        let Some(index) = indexes.get_mut(vault) else {
+            return;
+        };
+
+        if changes.rescan {
+            if let Err(e) = index.refresh(vault) {
+                log::warn!("{e}");
+            }
+            return;
+        }
+        let touched = changes
+            .created
+            .iter()
+            .chain(&changes.modified)
+            .chain(&changes.deleted)
+            .chain(changes.renamed.iter().flat_map(|r| [&r.from, &r.to]));
+        for rel_path in touched {
+            index.update_note(vault, rel_path);
+        }
+    }
+}
+
+pub fn search_notes_impl(
+    store: &SearchIndexStore,
+    vault_path: &str,
+    query: &str,
+    options: SearchOptions,
+) -> VaultResult<Vec<SearchHit>> {
+    let query = Query::parse(query);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (vault, ranked) = store.with_index(vault_path, options.refresh, |vault, index| {
+        (vault.to_path_buf(), index.rank(&query, options.show_hidden))
+    })?;
+
+    let terms: Vec<&Term> = query.positive_terms().collect();
+    let hits = ranked
+        .into_iter()
+        .take(options.limit.unwrap_or(DEFAULT_LIMIT))
+        .map(|(rel_path, score)| {
+            // Snippets are cut from the current file rather than stored text.
+            let snippets = std::fs::read_to_string(vault.join(&rel_path))
+                .map(|text| build_snippets(&text, &terms))
+                .unwrap_or_default();
+            SearchHit {
+                rel_path,
+                score,
+                snippets,
+            }
+        })
+        .collect();
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_snippets, search_notes_impl, Query, SearchIndex, SearchIndexStore, SearchOptions,
+        Term,
+    };
+    use crate::index::LinkIndexStore;
+    use crate::rename::rename_note_impl;
+    use crate::testing::TempDir;
+    use crate::trash::delete_note_impl;
+
+    fn index(notes: &[(&str, &str)]) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for (rel_path, text) in notes {
+            index.insert(rel_path, 0, text.len() as u64, text);
+        }
+        index
+    }
+
+    fn search(index: &SearchIndex, query: &str) -> Vec<String> {
+        let options = SearchOptions::default();
+        index
+            .rank(&Query::parse(query), options.show_hidden)
+            .into_iter()
+            .map(|(rel_path, _)| rel_path)
+            .collect()
+    }
+
+    #[test]
+    fn parses_queries() {
+        let query =
+            Query::parse(r#"rust "exact phrase" pre* a OR b NOT c -d path:"My Dir" folder:/x/"#);
+        assert_eq!(
+            query.clauses,
+            vec![
+                vec![Term::Word("rust".into())],
+                vec![Term::Phrase(vec!["exact".into(), "phrase".into()])],
+                vec![Term::Prefix("pre".into())],
+                vec![Term::Word("a".into()), Term::Word("b".into())],
+            ]
+        );
+        assert_eq!(
+            query.excluded,
+            vec![Term::Word("c".into()), Term::Word("d".into())]
+        );
+        assert_eq!(query.paths, vec!["my dir".to_string()]);
+        assert_eq!(query.folders, vec!["x/".to_string()]);
+    }
+
+    #[test]
+    fn matches_boolean_phrase_and_prefix_queries() {
+        let index = index(&[
+            ("a.md", "The quick brown fox"),
+            ("b.md", "brown quick dogs"),
+            ("notes/c.md", "A fox and a dog"),
+        ]);
+
+        assert_eq!(search(&index, "\"quick brown\""), vec!["a.md"]);
+        assert_eq!(search(&index, "brown -fox"), vec!["b.md"]);
+        assert_eq!(search(&index, "dog*").len(), 2);
+        assert_eq!(search(&index, "fox folder:notes"), vec!["notes/c.md"]);
+        assert_eq!(search(&index, "fox -folder:notes"), vec!["a.md"]);
+        let mut either = search(&index, "dogs OR fox");
+        either.sort();
+        assert_eq!(either, vec!["a.md", "b.md", "notes/c.md"]);
+    }
+
+    #[test]
+    fn ranks_denser_matches_first() {
+        let mut index = index(&[
+            ("once.md", "search once among many other words here"),
+            ("twice.md", "search search"),
+        ]);
+        assert_eq!(search(&index, "search"), vec!["twice.md", "once.md"]);
+
+        index.remove("twice.md");
+        assert_eq!(search(&index, "search"), vec!["once.md"]);
+        assert!(!index.postings.contains_key("twice"));
+    }
+
+    #[test]
+    fn highlights_snippets_by_line() {
+        let terms = [Term::Word("fox".into())];
+        let terms: Vec<&Term> = terms.iter().collect();
+        let snippets = build_snippets("intro\n  the Fox jumps\nno match", &terms);
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets[0].line, 2);
+        assert_eq!(snippets[0].text, "the Fox jumps");
+        assert_eq!(
+            &snippets[0].text[snippets[0].highlights[0].start..snippets[0].highlights[0].end],
+            "Fox"
+        );
+    }
+
+    #[test]
+    fn store_rescans_only_when_asked() {
+        let dir = TempDir::new("search-store");
+        std::fs::write(dir.join("One.md"), "apple").unwrap();
+        let vault = dir.to_string_lossy().to_string();
+        let store = SearchIndexStore::default();
+        let hits = |refresh| -> Vec<String> {
+            let options = SearchOptions {
+                refresh,
+                ..SearchOptions::default()
+            };
+            search_notes_impl(&store, &vault, "apple", options)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.rel_path)
+                .collect()
+        };
+        assert_eq!(hits(false), vec!["One.md"]);
+
+        // Without a watcher event the new note stays unseen until a refresh.
+        std::fs::write(dir.join("Two.md"), "apple").unwrap();
+        assert_eq!(hits(false), vec!["One.md"]);
+        assert_eq!(hits(true).len(), 2);
+
+        std::fs::write(dir.join("Three.md"), "apple").unwrap();
+        store.notes_changed(&vault, &["Three.md".to_string()]);
+        assert_eq!(hits(false).len(), 3);
+    }
+
+    #[test]
+    fn store_follows_renames_and_deletes() {
+        let dir = TempDir::new("search-moves");
+        std::fs::create_dir_all(dir.join(".hidden")).unwrap();
+        std::fs::write(dir.join("Apple.md"), "apple pie").unwrap();
+        std::fs::write(dir.join("Ref.md"), "[[Apple]] recipe").unwrap();
+        std::fs::write(dir.join(".hidden/Secret.md"), "apple").unwrap();
+        let vault = dir.to_string_lossy().to_string();
+        let links = LinkIndexStore::new(None);
+        let store = SearchIndexStore::default();
+        let hits = |query: &str| -> Vec<String> {
+            let options = SearchOptions {
+                show_hidden: true,
+                ..SearchOptions::default()
+            };
+            let mut hits: Vec<String> = search_notes_impl(&store, &vault, query, options)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.rel_path)
+                .collect();
+            hits.sort();
+            hits
+        };
+        // What the app's commands do after each change.
+        let changed = |rel_paths: &[String]| {
+            links.notes_changed(&vault, rel_paths);
+            store.notes_changed(&vault, rel_paths);
+        };
+        assert_eq!(
+            hits("apple"),
+            vec![".hidden/Secret.md", "Apple.md", "Ref.md"]
+        );
+
+        let report = rename_note_impl(&links, &vault, "Apple.md", "fruit/Pear.md").unwrap();
+        changed(&report.changed_paths());
+        assert_eq!(hits("apple"), vec![".hidden/Secret.md", "fruit/Pear.md"]);
+        assert_eq!(hits("pear"), vec!["Ref.md"]);
+
+        let deleted = delete_note_impl(&links, &vault, ".hidden").unwrap();
+        changed(&[deleted.entry.original_rel_path]);
+        assert_eq!(hits("apple"), vec!["fruit/Pear.md"]);
+        let indexed = links
+            .with_index(&vault, |_, index| index.notes().count())
+            .unwrap();
+        assert_eq!(indexed, 2);
+    }
+
+    #[test]
+    fn lists_notes_for_filter_only_queries() {
+        let dir = TempDir::new("search-filters");
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        std::fs::write(dir.join("a.md"), "apple").unwrap();
+        std::fs::write(dir.join("notes/b.md"), "banana").unwrap();
+        std::fs::write(dir.join("notes/c.md"), "apple cake").unwrap();
+        let vault = dir.to_string_lossy().to_string();
+        let store = SearchIndexStore::default();
+        let options: SearchOptions = serde_json::from_str(r#"{"showHidden":true}"#).unwrap();
+        assert!(options.show_hidden);
+        let hits = |query: &str| -> Vec<String> {
+            search_notes_impl(&store, &vault, query, SearchOptions::default())
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.rel_path)
+                .collect()
+        };
+
+        assert_eq!(hits("folder:notes"), vec!["notes/b.md", "notes/c.md"]);
+        assert_eq!(hits("-apple"), vec!["notes/b.md"]);
+        assert_eq!(hits("NOT banana -path:c"), vec!["a.md"]);
+        assert!(hits("  ").is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::error::VaultResult;
+use crate::frontmatter::body_start;
+use crate::graph::is_hidden_path;
+use crate::index::LinkIndexStore;
+use crate::wikilinks::{code_span_ranges, fenced_code_ranges};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A tag in the tag hierarchy, e.g. `area` with child `area/sub`.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct TagNode {
+    /// Full tag path without `#`, e.g. `area/sub`
+    pub tag: String,
+    /// Last segment of the tag path, e.g. `sub`
+    pub name: String,
+    /// Notes carrying exactly this tag
+    pub count: usize,
+    /// Notes carrying this tag or any nested tag
+    pub total: usize,
+    pub children: Vec<TagNode>,
+}
+
+/// Normalize a tag as written in a note or query: no `#`, no surrounding
+/// slashes, lowercase.
+pub(crate) fn normalize_tag(tag: &str) -> String {
+    tag.trim()
+        .trim_start_matches('#')
+        .trim_matches('/')
+        .to_lowercase()
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+/// Inline `#tags` of a note body, normalized and deduplicated in document
+/// order. Tags inside code, headings markers, links and URLs are ignored.
+pub(crate) fn extract_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let fences = fenced_code_ranges(text);
+    let body = body_start(text);
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if line_start < body
+            || fences
+                .iter()
+                .any(|(start, end)| line_start >= *start && line_start < *end)
+        {
+            continue;
+        }
+
+        let spans = code_span_ranges(line);
+        let mut prev: Option<char> = None;
+        for (idx, c) in line.char_indices() {
+            let at_boundary = prev.map_or(true, char::is_whitespace);
+            prev = Some(c);
+            if c != '#' || !at_boundary || spans.iter().any(|(s, e)| idx >= *s && idx < *e) {
+                continue;
+            }
+
+            let rest = &line[idx + 1..];
+            let len = rest
+                .char_indices()
+                .find(|(_, c)| !is_tag_char(*c))
+                .map_or(rest.len(), |(i, _)| i);
+            let tag = normalize_tag(&rest[..len]);
+            // Pure numbers such as issue references (#123) are not tags.
+            if tag.is_empty() || tag.chars().all(|c| c.is_ascii_digit() || c == '/') {
+                continue;
+            }
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+/// Inline and frontmatter tags of a note, deduplicated.
+pub(crate) fn note_tags(text: &str, frontmatter_tags: &[String]) -> Vec<String> {
+    let mut tags = extract_tags(text);
+    for tag in frontmatter_tags {
+        let tag = normalize_tag(tag);
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Whether `tag` is `query` or nested below it.
+fn tag_matches(tag: &str, query: &str) -> bool {
+    tag == query
+        || tag
+            .strip_prefix(query)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Build the tag hierarchy from each note's tag list.
+fn build_tag_tree<'a>(notes: impl Iterator<Item = (&'a str, &'a [String])>) -> Vec<TagNode> {
+    let mut exact: BTreeMap<String, usize> = BTreeMap::new();
+    let mut nested: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+    for (rel_path, tags) in notes {
+        for tag in tags {
+            *exact.entry(tag.clone()).or_default() += 1;
+            // Register every ancestor so `a/b/c` also counts towards `a/b` and `a`.
+            let mut end = 0;
+            for segment in tag.split('/') {
+                end += segment.len();
+                nested
+                    .entry(tag[..end].to_string())
+                    .or_default()
+                    .insert(rel_path);
+                end += 1;
+            }
+        }
+    }
+
+    let mut roots: Vec<TagNode> = Vec::new();
+    // BTreeMap order visits parents before their children.
+    for (tag, notes) in &nested {
+        let node = TagNode {
+            tag: tag.clone(),
+            name: tag.rsplit('/').next().unwrap_or(tag).to_string(),
+            count: exact.get(tag).copied().unwrap_or(0),
+            total: notes.len(),
+            children: Vec::new(),
+        };
+        let mut siblings = &mut roots;
+        for (idx, _) in tag.match_indices('/') {
+            let parent = &tag[..idx];
+            let Some(pos) = siblings.iter().position(|n| n.tag == parent) else {
+                break;
+            };
+            siblings = &mut siblings[pos].children;
+        }
+        siblings.push(node);
+    }
+    roots
+}
+
+pub fn list_tags_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    show_hidden: bool,
+) -> VaultResult<Vec<TagNode>> {
+    store.with_index(vault_path, |_, index| {
+        build_tag_tree(
+            index
+                .notes()
+                .filter(|(rel_path, _)| show_hidden || !is_hidden_path(rel_path))
+                .map(|(rel_path, note)| (rel_path.as_str(), note.tags.as_slice())),
+        )
+    })
+}
+
+/// Notes tagged with `tag` or any tag nested below it, in rel_path order.
+pub fn notes_with_tag_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    tag: &str,
+    show_hidden: bool,
+) -> VaultResult<Vec<String>> {
+    let query = normalize_tag(tag);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    store.with_index(vault_path, |_, index| {
+        index
+            .notes()
+            .filter(|(rel_path, _)| show_hidden || !is_hidden_path(rel_path))
+            .filter(|(_, note)| note.tags.iter().any(|t| tag_matches(t, &query)))
+            .map(|(rel_path, _)| rel_path.clone())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_tag_tree, extract_tags, tag_matches};
+
+    #[test]
+    fn extracts_inline_tags() {
+        let text = "---\ntags: [front]\n---\n# Heading #Area/Sub\nSee https://x.com/#anchor and `#code` [[Note#Section]]\n```\n#fenced\n```\n#123 #todo, (#nope) #todo\n";
+        assert_eq!(extract_tags(text), vec!["area/sub", "todo"]);
+    }
+
+    #[test]
+    fn builds_hierarchy() {
+        let a = vec!["area/sub".to_string(), "todo".to_string()];
+        let b = vec!["area".to_string(), "area/sub/deep".to_string()];
+        let tree = build_tag_tree([("a.md", a.as_slice()), ("b.md", b.as_slice())].into_iter());
+
+        assert_eq!(tree.len(), 2);
+        let area = &tree[0];
+        assert_eq!((area.tag.as_str(), area.count, area.total), ("area", 1, 2));
+        let sub = &area.children[0];
+        assert_eq!((sub.name.as_str(), sub.count, sub.total), ("sub", 1, 2));
+        assert_eq!(sub.children[0].tag, "area/sub/deep");
+        assert_eq!(tree[1].tag, "todo");
+
+        assert!(tag_matches("area/sub", "area"));
+        assert!(!tag_matches("areas", "area"));
+    }
+}
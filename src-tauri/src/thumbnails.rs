@@ -0,0 +1,231 @@
+use crate::error::{VaultError, VaultResult};
+use crate::vault::{
+    content_hash, mime_for_path, read_vault_image_impl, resolve_existing_asset_path, write_atomic,
+    VaultImage,
+};
+use image::{DynamicImage, ImageFormat};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Thumbnails kept on disk before the least recently used are evicted.
+const DEFAULT_MAX_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 85;
+
+/// Downscaled copies of vault images, persisted so large photos are decoded
+/// once per modification rather than on every preview.
+pub struct ThumbnailCache {
+    dir: Option<PathBuf>,
+    max_bytes: u64,
+    /// Serialises writes so concurrent evictions do not race each other.
+    lock: Mutex<()>,
+}
+
+impl ThumbnailCache {
+    /// Create a cache storing thumbnails under `dir`. Without a directory
+    /// thumbnails are still produced, just never reused.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self::with_limit(dir, DEFAULT_MAX_CACHE_BYTES)
+    }
+
+    fn with_limit(dir: Option<PathBuf>, max_bytes: u64) -> Self {
+        ThumbnailCache {
+            dir,
+            max_bytes,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn cache_file(&self, path: &Path, mtime_ms: u64, max_dimension: u32) -> Option<PathBuf> {
+        let key =
+            content_hash(format!("{}\0{mtime_ms}\0{max_dimension}", path.display()).as_bytes());
+        self.dir.as_ref().map(|dir| dir.join(key))
+    }
+
+    /// A cached thumbnail, with its last use bumped so eviction spares it.
+    fn load(&self, file: &Path) -> Option<(Vec<u8>, String)> {
+        for ext in ["png", "jpg"] {
+            let path = file.with_extension(ext);
+            let Ok(bytes) = std::fs::read(&path) else {
+                continue;
+            };
+            if let Ok(handle) = std::fs::File::options().write(true).open(&path) {
+                let _ = handle.set_modified(SystemTime::now());
+            }
+            return Some((bytes, mime_for_path(&path)));
+        }
+        None
+    }
+
+    fn store(&self, file: &Path, ext: &str, bytes: &[u8]) -> VaultResult<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| VaultError::io(e, "failed to create thumbnail folder"))?;
+        }
+        let path = file.with_extension(ext);
+        write_atomic(&path, bytes)?;
+        if let Some(dir) = file.parent() {
+            self.evict(dir, &path);
+        }
+        Ok(())
+    }
+
+    /// Remove the least recently used thumbnails until the cache fits, always
+    /// keeping the one just written.
+    fn evict(&self, dir: &Path, keep: &Path) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let modified = metadata.modified().ok()?;
+                let path = entry.path();
+                (metadata.is_file() && path != keep).then_some((modified, metadata.len(), path))
+            })
+            .collect();
+        let kept = std::fs::metadata(keep).map_or(0, |metadata| metadata.len());
+        let mut total: u64 = kept + files.iter().map(|(_, size, _)| size).sum::<u64>();
+        files.sort_by_key(|(modified, _, _)| *modified);
+        for (_, size, path) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("failed to evict thumbnail {}: {e}", path.display());
+                continue;
+            }
+            total -= size;
+        }
+    }
+}
+
+fn thumbnail_format(path: &Path) -> Option<ImageFormat> {
+    match mime_for_path(path).as_str() {
+        "image/png" => Some(ImageFormat::Png),
+        "image/jpeg" => Some(ImageFormat::Jpeg),
+        "image/webp" => Some(ImageFormat::WebP),
+        _ => None,
+    }
+}
+
+/// Encode as PNG when transparency must survive, JPEG otherwise.
+fn encode_thumbnail(image: &DynamicImage) -> VaultResult<(Vec<u8>, &'static str)> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    let ext = if image.color().has_alpha() {
+        image
+            .write_to(&mut bytes, ImageFormat::Png)
+            .map_err(|e| VaultError::other(format!("failed to encode thumbnail: {e}")))?;
+        "png"
+    } else {
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY);
+        image
+            .to_rgb8()
+            .write_with_encoder(encoder)
+            .map_err(|e| VaultError::other(format!("failed to encode thumbnail: {e}")))?;
+        "jpg"
+    };
+    Ok((bytes.into_inner(), ext))
+}
+
+/// Read an image asset scaled down to fit within `max_dimension` pixels on
+/// both sides. PNG, JPEG and WebP images larger than that are decoded,
+/// downscaled and cached by path and modification time; anything else, and
+/// images already small enough, come back unchanged. `mtime_ms` is always
+/// the original file's.
+pub fn read_vault_thumbnail_impl(
+    cache: &ThumbnailCache,
+    vault_path: &str,
+    rel_path: &str,
+    max_dimension: u32,
+) -> VaultResult<VaultImage> {
+    if max_dimension == 0 {
+        return Err(VaultError::Invalid(
+            "max dimension must be positive".to_string(),
+        ));
+    }
+    let path = resolve_existing_asset_path(vault_path, rel_path)?;
+    let Some(format) = thumbnail_format(&path) else {
+        return read_vault_image_impl(vault_path, rel_path);
+    };
+
+    let original = read_vault_image_impl(vault_path, rel_path)?;
+    let cache_file = cache.cache_file(&path, original.mtime_ms, max_dimension);
+    if let Some((bytes, mime)) = cache_file.as_deref().and_then(|file| cache.load(file)) {
+        return Ok(VaultImage {
+            bytes,
+            mime,
+            mtime_ms: original.mtime_ms,
+        });
+    }
+
+    let image = match image::load_from_memory_with_format(&original.bytes, format) {
+        Ok(image) => image,
+        Err(e) => {
+            log::warn!("failed to decode {rel_path} for a thumbnail: {e}");
+            return Ok(original);
+        }
+    };
+    if image.width() <= max_dimension && image.height() <= max_dimension {
+        return Ok(original);
+    }
+
+    let (bytes, ext) = encode_thumbnail(&image.thumbnail(max_dimension, max_dimension))?;
+    if let Some(file) = cache_file {
+        if let Err(e) = cache.store(&file, ext, &bytes) {
+            log::warn!("failed to cache thumbnail for {rel_path}: {e}");
+        }
+    }
+    Ok(VaultImage {
+        bytes,
+        mime: if ext == "png" {
+            "image/png"
+        } else {
+            "image/jpeg"
+        }
+        .to_string(),
+        mtime_ms: original.mtime_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_vault_thumbnail_impl, ThumbnailCache};
+    use crate::testing::TempDir;
+    use image::{ImageFormat, RgbImage, RgbaImage};
+
+    #[test]
+    fn downscales_and_caches_large_images() {
+        let dir = TempDir::new("thumbnails");
+        let vault = dir.join("vault");
+        std::fs::create_dir_all(&vault).unwrap();
+        RgbImage::new(400, 200)
+            .save_with_format(vault.join("photo.jpg"), ImageFormat::Jpeg)
+            .unwrap();
+        RgbaImage::new(300, 300)
+            .save_with_format(vault.join("icon.png"), ImageFormat::Png)
+            .unwrap();
+        RgbImage::new(50, 20)
+            .save_with_format(vault.join("small.png"), ImageFormat::Png)
+            .unwrap();
+        let vault_path = vault.to_string_lossy().to_string();
+        let cache = ThumbnailCache::with_limit(Some(dir.join("cache")), 1);
+
+        let photo = read_vault_thumbnail_impl(&cache, &vault_path, "photo.jpg", 100).unwrap();
+        assert_eq!(photo.mime, "image/jpeg");
+        let decoded = image::load_from_memory(&photo.bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 50));
+        let cached = read_vault_thumbnail_impl(&cache, &vault_path, "photo.jpg", 100).unwrap();
+        assert_eq!(cached.bytes, photo.bytes);
+
+        let icon = read_vault_thumbnail_impl(&cache, &vault_path, "icon.png", 100).unwrap();
+        assert_eq!(icon.mime, "image/png");
+        // The one-byte limit leaves only the newest thumbnail behind.
+        assert_eq!(std::fs::read_dir(dir.join("cache")).unwrap().count(), 1);
+
+        let small = read_vault_thumbnail_impl(&cache, &vault_path, "small.png", 100).unwrap();
+        assert_eq!(small.bytes, std::fs::read(vault.join("small.png")).unwrap());
+    }
+}
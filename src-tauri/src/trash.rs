@@ -0,0 +1,363 @@
+use crate::error::{VaultError, VaultResult};
+use crate::index::LinkIndexStore;
+use crate::vault::{
+    content_hash, path_to_rel_string, resolve_existing_folder_path, resolve_existing_note_path,
+    resolve_folder_path_for_create, resolve_note_path_for_create, resolve_vault, sanitize_rel_path,
+    write_atomic,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Vault-local folder holding deleted notes and folders.
+pub const TRASH_DIR: &str = ".trash";
+
+/// Metadata stored next to every trashed item as `<id>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    /// Identifier of the entry inside the trash folder
+    pub id: String,
+    /// Where the item lived before it was deleted
+    pub original_rel_path: String,
+    /// Deletion timestamp in milliseconds
+    pub deleted_at_ms: u64,
+    /// Whether the item is a folder rather than a single note
+    pub is_folder: bool,
+}
+
+/// Result of moving a note or folder to the trash.
+#[derive(Debug, Serialize)]
+pub struct DeleteReport {
+    pub entry: TrashEntry,
+    /// Notes that still link to the deleted note(s)
+    pub linked_from: Vec<String>,
+}
+
+fn trash_root(vault: &Path) -> PathBuf {
+    vault.join(TRASH_DIR)
+}
+
+pub(crate) fn is_in_trash(rel_path: &str) -> bool {
+    rel_path == TRASH_DIR || rel_path.starts_with(&format!("{TRASH_DIR}/"))
+}
+
+/// Trash ids are generated by us; reject anything that could be a path.
+fn validate_id(id: &str) -> VaultResult<()> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(VaultError::Invalid("invalid trash entry id".to_string()));
+    }
+    Ok(())
+}
+
+fn entry_meta_path(vault: &Path, id: &str) -> PathBuf {
+    trash_root(vault).join(format!("{id}.json"))
+}
+
+fn read_entry(vault: &Path, id: &str) -> VaultResult<TrashEntry> {
+    validate_id(id)?;
+    let raw = std::fs::read_to_string(entry_meta_path(vault, id))
+        .map_err(|e| VaultError::io(e, "failed to read trash entry"))?;
+    serde_json::from_str(&raw).map_err(|e| VaultError::other(format!("invalid trash entry: {e}")))
+}
+
+/// The moved item keeps its original file name inside `.trash/<id>/`.
+fn entry_item_path(vault: &Path, entry: &TrashEntry) -> PathBuf {
+    let name = entry
+        .original_rel_path
+        .rsplit('/')
+        .next()
+        .unwrap_or(&entry.original_rel_path);
+    trash_root(vault).join(&entry.id).join(name)
+}
+
+fn new_entry_id(vault: &Path, rel_path: &str, deleted_at_ms: u64) -> String {
+    let base = format!(
+        "{deleted_at_ms}-{}",
+        &content_hash(rel_path.as_bytes())[..8]
+    );
+    let mut id = base.clone();
+    let mut n = 1;
+    while trash_root(vault).join(&id).exists() || entry_meta_path(vault, &id).exists() {
+        n += 1;
+        id = format!("{base}-{n}");
+    }
+    id
+}
+
+pub fn delete_note_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    rel_path: &str,
+) -> VaultResult<DeleteReport> {
+    let vault = resolve_vault(vault_path)?;
+    let clean = sanitize_rel_path(rel_path)?;
+    let is_folder = vault.join(&clean).is_dir();
+    let source = if is_folder {
+        resolve_existing_folder_path(vault_path, rel_path)?
+    } else {
+        resolve_existing_note_path(vault_path, rel_path)?
+    };
+    let original_rel_path = path_to_rel_string(&vault, &source)?;
+    if is_in_trash(&original_rel_path) {
+        return Err(VaultError::Invalid(
+            "path is already in the trash".to_string(),
+        ));
+    }
+
+    // Collect incoming links before the notes disappear from the index.
+    let linked_from = store.with_index(vault_path, |_, index| {
+        let prefix = format!("{original_rel_path}/");
+        let deleted: HashSet<&str> = index
+            .notes()
+            .map(|(note, _)| note.as_str())
+            .filter(|note| {
+                if is_folder {
+                    note.starts_with(&prefix)
+                } else {
+                    *note == original_rel_path
+                }
+            })
+            .collect();
+        index.linked_from(&deleted)
+    })?;
+
+    let deleted_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let entry = TrashEntry {
+        id: new_entry_id(&vault, &original_rel_path, deleted_at_ms),
+        original_rel_path,
+        deleted_at_ms,
+        is_folder,
+    };
+
+    let item = entry_item_path(&vault, &entry);
+    if let Some(parent) = item.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| VaultError::io(e, "failed to create trash folder"))?;
+    }
+    std::fs::rename(&source, &item).map_err(|e| VaultError::io(e, "failed to move to trash"))?;
+
+    let json = serde_json::to_string(&entry)
+        .map_err(|e| VaultError::other(format!("failed to encode trash entry: {e}")))?;
+    if let Err(e) = write_atomic(&entry_meta_path(&vault, &entry.id), json.as_bytes()) {
+        // Without metadata the item could never be restored; undo the move.
+        let _ = std::fs::rename(&item, &source);
+        let _ = std::fs::remove_dir(trash_root(&vault).join(&entry.id));
+        return Err(e);
+    }
+
+    Ok(DeleteReport { entry, linked_from })
+}
+
+/// Move a folder and everything in it to the trash. Unlike
+/// `delete_note_impl`, refuses anything that is not a folder.
+pub fn delete_folder_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    rel_path: &str,
+) -> VaultResult<DeleteReport> {
+    resolve_existing_folder_path(vault_path, rel_path)?;
+    delete_note_impl(store, vault_path, rel_path)
+}
+
+pub fn restore_note_impl(vault_path: &str, id: &str) -> VaultResult<TrashEntry> {
+    let vault = resolve_vault(vault_path)?;
+    let entry = read_entry(&vault, id)?;
+    let item = entry_item_path(&vault, &entry);
+    if !item.exists() {
+        return Err(VaultError::NotFound(
+            "trash entry is missing its contents".to_string(),
+        ));
+    }
+
+    // The metadata file is editable, so the path gets the same checks as
+    // any other write.
+    let dest = if entry.is_folder {
+        resolve_folder_path_for_create(vault_path, &entry.original_rel_path)?
+    } else {
+        resolve_note_path_for_create(vault_path, &entry.original_rel_path)?
+    };
+    if is_in_trash(&path_to_rel_string(&vault, &dest)?) {
+        return Err(VaultError::Invalid(
+            "trash entry points into the trash".to_string(),
+        ));
+    }
+    if dest.exists() {
+        return Err(VaultError::AlreadyExists(
+            "a note or folder already exists at the original path".to_string(),
+        ));
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| VaultError::io(e, "failed to create note folder"))?;
+    }
+
+    std::fs::rename(&item, &dest).map_err(|e| VaultError::io(e, "failed to restore from trash"))?;
+    remove_entry(&vault, &entry.id)?;
+    Ok(entry)
+}
+
+pub fn list_trash_impl(vault_path: &str) -> VaultResult<Vec<TrashEntry>> {
+    let vault = resolve_vault(vault_path)?;
+    let root = trash_root(&vault);
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let read_dir =
+        std::fs::read_dir(&root).map_err(|e| VaultError::io(e, "failed to read trash"))?;
+    let mut entries: Vec<TrashEntry> = Vec::new();
+    for dir_entry in read_dir {
+        let dir_entry = dir_entry.map_err(|e| VaultError::io(e, "failed to read entry"))?;
+        let path = dir_entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        match read_entry(&vault, id) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::warn!("skipping trash entry {id}: {e}"),
+        }
+    }
+
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at_ms));
+    Ok(entries)
+}
+
+fn remove_entry(vault: &Path, id: &str) -> VaultResult<()> {
+    let dir = trash_root(vault).join(id);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .map_err(|e| VaultError::io(e, "failed to purge trash entry"))?;
+    }
+    let meta = entry_meta_path(vault, id);
+    if meta.exists() {
+        std::fs::remove_file(&meta)
+            .map_err(|e| VaultError::io(e, "failed to purge trash entry"))?;
+    }
+    Ok(())
+}
+
+/// Ids of everything in the trash folder, including items whose metadata is
+/// missing or unreadable and which `list_trash_impl` therefore skips.
+fn all_trash_ids(vault: &Path) -> VaultResult<Vec<String>> {
+    let root = trash_root(vault);
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let read_dir =
+        std::fs::read_dir(&root).map_err(|e| VaultError::io(e, "failed to read trash"))?;
+    let mut ids = BTreeSet::new();
+    for dir_entry in read_dir {
+        let path = dir_entry
+            .map_err(|e| VaultError::io(e, "failed to read entry"))?
+            .path();
+        let id = if path.is_dir() {
+            path.file_name()
+        } else if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            path.file_stem()
+        } else {
+            None
+        };
+        if let Some(id) = id.and_then(|id| id.to_str()) {
+            if validate_id(id).is_ok() {
+                ids.insert(id.to_string());
+            }
+        }
+    }
+    Ok(ids.into_iter().collect())
+}
+
+/// Permanently delete the given trash entries, or the whole trash when `ids`
+/// is `None`. Returns the ids that were purged.
+pub fn purge_trash_impl(vault_path: &str, ids: Option<Vec<String>>) -> VaultResult<Vec<String>> {
+    let vault = resolve_vault(vault_path)?;
+    let ids = match ids {
+        Some(ids) => ids,
+        None => all_trash_ids(&vault)?,
+    };
+
+    for id in &ids {
+        validate_id(id)?;
+        remove_entry(&vault, id)?;
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        delete_note_impl, is_in_trash, list_trash_impl, purge_trash_impl, restore_note_impl,
+        validate_id,
+    };
+    use crate::error::VaultError;
+    use crate::index::LinkIndexStore;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn trash_paths() {
+        assert!(is_in_trash(".trash"));
+        assert!(is_in_trash(".trash/123/Note.md"));
+        assert!(!is_in_trash(".trashy/Note.md"));
+        assert!(!is_in_trash("notes/.trash.md"));
+    }
+
+    #[test]
+    fn trash_ids() {
+        assert!(validate_id("1700000000000-deadbeef").is_ok());
+        assert!(validate_id("1700000000000-deadbeef-2").is_ok());
+        assert!(validate_id("").is_err());
+        assert!(validate_id("../escape").is_err());
+        assert!(validate_id("a/b").is_err());
+    }
+
+    #[test]
+    fn deletes_restores_and_purges() {
+        let dir = TempDir::new("trash-round-trip");
+        std::fs::create_dir_all(dir.join("folder")).unwrap();
+        std::fs::write(dir.join("Note.md"), "note").unwrap();
+        std::fs::write(dir.join("Ref.md"), "see [[Note]]").unwrap();
+        std::fs::write(dir.join("folder/Inner.md"), "inner").unwrap();
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+
+        let note = delete_note_impl(&store, &vault, "Note.md").unwrap();
+        assert_eq!(note.linked_from, vec!["Ref.md".to_string()]);
+        assert!(!note.entry.is_folder);
+        let folder = delete_note_impl(&store, &vault, "folder").unwrap();
+        assert!(folder.entry.is_folder);
+        assert!(!dir.join("folder").exists());
+        assert_eq!(list_trash_impl(&vault).unwrap().len(), 2);
+
+        std::fs::write(dir.join("Note.md"), "replacement").unwrap();
+        match restore_note_impl(&vault, &note.entry.id) {
+            Err(VaultError::AlreadyExists(_)) => {}
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+        std::fs::remove_file(dir.join("Note.md")).unwrap();
+        restore_note_impl(&vault, &note.entry.id).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("Note.md")).unwrap(),
+            "note"
+        );
+        restore_note_impl(&vault, &folder.entry.id).unwrap();
+        assert!(dir.join("folder/Inner.md").is_file());
+        assert!(list_trash_impl(&vault).unwrap().is_empty());
+
+        delete_note_impl(&store, &vault, "Note.md").unwrap();
+        delete_note_impl(&store, &vault, "folder").unwrap();
+        // An item whose metadata was lost is purged along with the rest.
+        std::fs::create_dir_all(dir.join(".trash/1700000000000-orphan/sub")).unwrap();
+        std::fs::write(dir.join(".trash/1700000000000-orphan/sub/Lost.md"), "lost").unwrap();
+        assert_eq!(purge_trash_impl(&vault, None).unwrap().len(), 3);
+        assert!(!dir.join(".trash/1700000000000-orphan").exists());
+        assert!(list_trash_impl(&vault).unwrap().is_empty());
+        assert!(!dir.join("Note.md").exists());
+    }
+}
@@ -0,0 +1,254 @@
+use crate::error::VaultResult;
+use crate::graph::is_hidden_path;
+use crate::index::{file_stamp, LinkIndexStore};
+use crate::vault::{
+    is_markdown_file, path_to_rel_string, resolve_existing_folder_path, resolve_vault,
+    sanitize_rel_path,
+};
+use crate::walk::walk_folder;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// What a vault tree entry stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TreeEntryKind {
+    Folder,
+    Note,
+    /// Any file that is not a Markdown note
+    Attachment,
+}
+
+/// A folder or file in the vault tree.
+#[derive(Debug, Serialize)]
+pub struct TreeEntry {
+    pub kind: TreeEntryKind,
+    /// File or folder name, with extension
+    pub name: String,
+    /// Relative path, empty for the vault root
+    pub rel_path: String,
+    /// Whether this entry matches the hidden rules (dotfiles, node_modules)
+    pub is_hidden: bool,
+    /// Size in bytes; for folders the total of their contents, or `None`
+    /// when part of it was not loaded
+    pub size: Option<u64>,
+    pub mtime_ms: Option<u64>,
+    /// Notes in this folder and every subfolder, loaded or not
+    pub note_count: usize,
+    /// Folders first, then files, each by name; `None` for files and for
+    /// folders that were not loaded yet
+    pub children: Option<Vec<TreeEntry>>,
+}
+
+fn entry_name(rel_path: &str) -> String {
+    rel_path.rsplit('/').next().unwrap_or(rel_path).to_string()
+}
+
+/// Sum sizes bottom-up and sort children.
+fn finish(entry: &mut TreeEntry) {
+    let Some(children) = entry.children.as_mut() else {
+        return;
+    };
+    let mut size = Some(0);
+    for child in children.iter_mut() {
+        finish(child);
+        size = size.zip(child.size).map(|(a, b)| a + b);
+    }
+    entry.size = size;
+    children.sort_by(|a, b| {
+        (b.kind == TreeEntryKind::Folder)
+            .cmp(&(a.kind == TreeEntryKind::Folder))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+}
+
+/// The vault as a tree of folders, notes and attachments, honouring the
+/// vault's ignore files. With `rel_path`, only that folder is listed; with
+/// `depth`, folders more than `depth` levels below it are returned without
+/// children so huge vaults can be loaded one folder at a time. Note counts
+/// come from the link index and always cover the whole folder.
+pub fn list_vault_tree_impl(
+    store: &LinkIndexStore,
+    vault_path: &str,
+    rel_path: Option<&str>,
+    depth: Option<usize>,
+    show_hidden: bool,
+) -> VaultResult<TreeEntry> {
+    let vault = resolve_vault(vault_path)?;
+    let (root, root_rel) = match rel_path.filter(|p| !p.is_empty()) {
+        Some(rel_path) => {
+            let folder = resolve_existing_folder_path(vault_path, rel_path)?;
+            let rel = path_to_rel_string(&vault, &folder)?;
+            // Walk the folder as named; it may be reached through a symlink.
+            (vault.join(sanitize_rel_path(rel_path)?), rel)
+        }
+        None => (vault.clone(), String::new()),
+    };
+
+    let note_counts = store.with_index(vault_path, |_, index| {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for (note, _) in index.notes() {
+            if !show_hidden && is_hidden_path(note) {
+                continue;
+            }
+            let mut folder = note.as_str();
+            while let Some((parent, _)) = folder.rsplit_once('/') {
+                *counts.entry(parent.to_string()).or_insert(0) += 1;
+                folder = parent;
+            }
+            *counts.entry(String::new()).or_insert(0) += 1;
+        }
+        counts
+    })?;
+
+    let walk = walk_folder(&vault, &root, depth, |_| true);
+    for warning in &walk.warnings {
+        log::warn!("skipped while listing the vault tree: {warning}");
+    }
+
+    let to_entry = |path: &Path, kind: TreeEntryKind| -> Option<TreeEntry> {
+        let rest = path_to_rel_string(&root, path).ok()?;
+        let level = rest.split('/').count();
+        let rel_path = if root_rel.is_empty() {
+            rest
+        } else {
+            format!("{root_rel}/{rest}")
+        };
+        let is_hidden = is_hidden_path(&rel_path);
+        if is_hidden && !show_hidden {
+            return None;
+        }
+        let stamp = file_stamp(path);
+        let is_folder = kind == TreeEntryKind::Folder;
+        Some(TreeEntry {
+            kind,
+            name: entry_name(&rel_path),
+            is_hidden,
+            size: if is_folder {
+                None
+            } else {
+                stamp.map(|(_, size, _)| size)
+            },
+            mtime_ms: stamp.map(|(mtime_ms, _, _)| mtime_ms),
+            note_count: note_counts.get(&rel_path).copied().unwrap_or(0),
+            // Folders at the depth limit were not walked into.
+            children: (is_folder && depth.map_or(true, |depth| level < depth)).then(Vec::new),
+            rel_path,
+        })
+    };
+
+    // Collect entries by rel_path, then attach them to their parents
+    // deepest first so every folder is complete before it is moved.
+    let mut entries: BTreeMap<String, TreeEntry> = BTreeMap::new();
+    let folders = walk
+        .folders
+        .iter()
+        .filter_map(|path| to_entry(path, TreeEntryKind::Folder));
+    let files = walk.files.iter().filter_map(|path| {
+        let kind = if is_markdown_file(path) {
+            TreeEntryKind::Note
+        } else {
+            TreeEntryKind::Attachment
+        };
+        to_entry(path, kind)
+    });
+    for entry in folders.chain(files) {
+        entries.insert(entry.rel_path.clone(), entry);
+    }
+
+    let stamp = file_stamp(&root);
+    let mut tree = TreeEntry {
+        kind: TreeEntryKind::Folder,
+        name: if root_rel.is_empty() {
+            vault
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        } else {
+            entry_name(&root_rel)
+        },
+        is_hidden: is_hidden_path(&root_rel),
+        size: None,
+        mtime_ms: stamp.map(|(mtime_ms, _, _)| mtime_ms),
+        note_count: note_counts.get(&root_rel).copied().unwrap_or(0),
+        children: Some(Vec::new()),
+        rel_path: root_rel.clone(),
+    };
+
+    let mut paths: Vec<String> = entries.keys().cloned().collect();
+    paths.sort_by_key(|path| std::cmp::Reverse(path.matches('/').count()));
+    for path in paths {
+        let Some(entry) = entries.remove(&path) else {
+            continue;
+        };
+        let parent = match path.rsplit_once('/') {
+            Some((parent, _)) if parent != root_rel => entries.get_mut(parent),
+            _ => Some(&mut tree),
+        };
+        // Entries under a hidden folder that was left out are dropped too.
+        if let Some(children) = parent.and_then(|p| p.children.as_mut()) {
+            children.push(entry);
+        }
+    }
+
+    finish(&mut tree);
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{list_vault_tree_impl, TreeEntry, TreeEntryKind};
+    use crate::index::LinkIndexStore;
+    use crate::testing::TempDir;
+
+    fn names(entry: &TreeEntry) -> Vec<&str> {
+        entry
+            .children
+            .iter()
+            .flatten()
+            .map(|child| child.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn lists_nested_folders_with_counts() {
+        let dir = TempDir::new("vault-tree");
+        for folder in ["empty", "notes/deep", ".obsidian"] {
+            std::fs::create_dir_all(dir.join(folder)).unwrap();
+        }
+        for (rel_path, text) in [
+            ("b.md", "12"),
+            ("A.md", "1"),
+            ("notes/C.md", "123"),
+            ("notes/deep/D.md", "1234"),
+            ("notes/image.png", "12345"),
+            (".obsidian/E.md", ""),
+        ] {
+            std::fs::write(dir.join(rel_path), text).unwrap();
+        }
+        let vault = dir.to_string_lossy().to_string();
+        let store = LinkIndexStore::new(None);
+
+        let tree = list_vault_tree_impl(&store, &vault, None, None, false).unwrap();
+        assert_eq!(names(&tree), vec!["empty", "notes", "A.md", "b.md"]);
+        assert_eq!((tree.note_count, tree.size), (4, Some(15)));
+        let notes = &tree.children.as_ref().unwrap()[1];
+        assert_eq!(names(notes), vec!["deep", "C.md", "image.png"]);
+        assert_eq!(
+            notes.children.as_ref().unwrap()[2].kind,
+            TreeEntryKind::Attachment
+        );
+
+        let shallow = list_vault_tree_impl(&store, &vault, Some("notes"), Some(1), true).unwrap();
+        assert_eq!(shallow.rel_path, "notes");
+        let deep = &shallow.children.as_ref().unwrap()[0];
+        assert_eq!((deep.rel_path.as_str(), deep.note_count), ("notes/deep", 1));
+        assert!(deep.children.is_none() && deep.size.is_none());
+        assert_eq!(shallow.size, None);
+
+        let all = list_vault_tree_impl(&store, &vault, None, Some(1), true).unwrap();
+        assert_eq!(names(&all)[0], ".obsidian");
+        assert!(all.children.as_ref().unwrap()[0].is_hidden);
+    }
+}
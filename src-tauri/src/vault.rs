@@ -1,11 +1,34 @@
-use serde::Serialize;
+use crate::error::{VaultError, VaultResult};
+use crate::frontmatter::NoteMetadata;
+use crate::trash::is_in_trash;
+use crate::walk::walk_vault;
+use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
+use std::io::Write;
 use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug, Serialize)]
 pub struct NoteEntry {
     pub rel_path: String,
     pub display_name: String,
+    /// Parsed frontmatter, when the listing was served from the link index
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<NoteMetadata>,
+}
+
+/// Identifies the on-disk state of a note when it was read or written.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteVersion {
+    pub mtime_ms: u64,
+    /// Hash of the raw file bytes
+    pub hash: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NoteContents {
+    pub contents: String,
+    pub version: NoteVersion,
 }
 
 #[derive(Debug, Serialize)]
@@ -15,7 +38,7 @@ pub struct VaultImage {
     pub mtime_ms: u64,
 }
 
-fn is_markdown_file(path: &Path) -> bool {
+pub(crate) fn is_markdown_file(path: &Path) -> bool {
     match path.extension().and_then(OsStr::to_str) {
         Some(ext) => {
             let ext = ext.to_ascii_lowercase();
@@ -25,23 +48,27 @@ fn is_markdown_file(path: &Path) -> bool {
     }
 }
 
-fn path_to_rel_string(vault: &Path, path: &Path) -> Result<String, String> {
+pub(crate) fn path_to_rel_string(vault: &Path, path: &Path) -> VaultResult<String> {
     let rel = path
         .strip_prefix(vault)
-        .map_err(|_| "path escapes vault".to_string())?;
+        .map_err(|_| VaultError::EscapesVault("path escapes vault".to_string()))?;
 
     let mut parts: Vec<String> = Vec::new();
     for component in rel.components() {
         match component {
             Component::Normal(p) => parts.push(p.to_string_lossy().to_string()),
             Component::CurDir => {}
-            _ => return Err("unsupported path component".to_string()),
+            _ => {
+                return Err(VaultError::Invalid(
+                    "unsupported path component".to_string(),
+                ))
+            }
         }
     }
     Ok(parts.join("/"))
 }
 
-fn display_name_for_path(path: &Path) -> String {
+pub(crate) fn display_name_for_path(path: &Path) -> String {
     let name = path
         .file_name()
         .and_then(OsStr::to_str)
@@ -55,35 +82,12 @@ fn display_name_for_path(path: &Path) -> String {
     name.to_string()
 }
 
-fn collect_markdown_files(
-    vault: &Path,
-    dir: &Path,
-    entries: &mut Vec<NoteEntry>,
-) -> Result<(), String> {
-    let read_dir = std::fs::read_dir(dir).map_err(|e| format!("failed to read directory: {e}"))?;
-    for entry in read_dir {
-        let entry = entry.map_err(|e| format!("failed to read entry: {e}"))?;
-        let path = entry.path();
-        if path.is_dir() {
-            collect_markdown_files(vault, &path, entries)?;
-            continue;
-        }
-        if path.is_file() && is_markdown_file(&path) {
-            let rel_path = path_to_rel_string(vault, &path)?;
-            entries.push(NoteEntry {
-                rel_path,
-                display_name: display_name_for_path(&path),
-            });
-        }
-    }
-
-    Ok(())
-}
-
-fn sanitize_rel_path(rel_path: &str) -> Result<PathBuf, String> {
+pub(crate) fn sanitize_rel_path(rel_path: &str) -> VaultResult<PathBuf> {
     let rel = Path::new(rel_path);
     if rel.is_absolute() {
-        return Err("absolute paths are not allowed".to_string());
+        return Err(VaultError::EscapesVault(
+            "absolute paths are not allowed".to_string(),
+        ));
     }
 
     let mut clean = PathBuf::new();
@@ -92,66 +96,110 @@ fn sanitize_rel_path(rel_path: &str) -> Result<PathBuf, String> {
             Component::Normal(part) => clean.push(part),
             Component::CurDir => {}
             Component::ParentDir => {
-                return Err("parent path components are not allowed".to_string())
+                return Err(VaultError::EscapesVault(
+                    "parent path components are not allowed".to_string(),
+                ))
+            }
+            _ => {
+                return Err(VaultError::Invalid(
+                    "unsupported path component".to_string(),
+                ))
             }
-            _ => return Err("unsupported path component".to_string()),
         }
     }
 
     if clean.as_os_str().is_empty() {
-        return Err("empty relative path".to_string());
+        return Err(VaultError::Invalid("empty relative path".to_string()));
     }
     Ok(clean)
 }
 
-fn resolve_existing_note_path(vault_path: &str, rel_path: &str) -> Result<PathBuf, String> {
+/// Canonical path of the vault root.
+pub(crate) fn resolve_vault(vault_path: &str) -> VaultResult<PathBuf> {
     let vault =
-        std::fs::canonicalize(vault_path).map_err(|e| format!("invalid vault path: {e}"))?;
+        std::fs::canonicalize(vault_path).map_err(|e| VaultError::io(e, "invalid vault path"))?;
     if !vault.is_dir() {
-        return Err("vault path is not a directory".to_string());
+        return Err(VaultError::Invalid(
+            "vault path is not a directory".to_string(),
+        ));
     }
+    Ok(vault)
+}
+
+pub(crate) fn resolve_existing_note_path(vault_path: &str, rel_path: &str) -> VaultResult<PathBuf> {
+    let vault = resolve_vault(vault_path)?;
 
     let rel = sanitize_rel_path(rel_path)?;
     let candidate = vault.join(rel);
     let candidate =
-        std::fs::canonicalize(&candidate).map_err(|e| format!("invalid note path: {e}"))?;
+        std::fs::canonicalize(&candidate).map_err(|e| VaultError::io(e, "invalid note path"))?;
 
     if !candidate.starts_with(&vault) {
-        return Err("note path escapes vault".to_string());
+        return Err(VaultError::EscapesVault(
+            "note path escapes vault".to_string(),
+        ));
     }
     if !candidate.is_file() {
-        return Err("note path is not a file".to_string());
+        return Err(VaultError::Invalid("note path is not a file".to_string()));
     }
     if !is_markdown_file(&candidate) {
-        return Err("note is not a markdown file".to_string());
+        return Err(VaultError::NotMarkdown(
+            "note is not a markdown file".to_string(),
+        ));
     }
 
     Ok(candidate)
 }
 
-fn resolve_existing_asset_path(vault_path: &str, rel_path: &str) -> Result<PathBuf, String> {
-    let vault =
-        std::fs::canonicalize(vault_path).map_err(|e| format!("invalid vault path: {e}"))?;
-    if !vault.is_dir() {
-        return Err("vault path is not a directory".to_string());
+pub(crate) fn resolve_existing_folder_path(
+    vault_path: &str,
+    rel_path: &str,
+) -> VaultResult<PathBuf> {
+    let vault = resolve_vault(vault_path)?;
+
+    let rel = sanitize_rel_path(rel_path)?;
+    let candidate = vault.join(rel);
+    let candidate =
+        std::fs::canonicalize(&candidate).map_err(|e| VaultError::io(e, "invalid folder path"))?;
+
+    if !candidate.starts_with(&vault) || candidate == vault {
+        return Err(VaultError::EscapesVault(
+            "folder path escapes vault".to_string(),
+        ));
     }
+    if !candidate.is_dir() {
+        return Err(VaultError::Invalid(
+            "folder path is not a directory".to_string(),
+        ));
+    }
+
+    Ok(candidate)
+}
+
+pub(crate) fn resolve_existing_asset_path(
+    vault_path: &str,
+    rel_path: &str,
+) -> VaultResult<PathBuf> {
+    let vault = resolve_vault(vault_path)?;
 
     let rel = sanitize_rel_path(rel_path)?;
     let candidate = vault.join(rel);
     let candidate =
-        std::fs::canonicalize(&candidate).map_err(|e| format!("invalid asset path: {e}"))?;
+        std::fs::canonicalize(&candidate).map_err(|e| VaultError::io(e, "invalid asset path"))?;
 
     if !candidate.starts_with(&vault) {
-        return Err("asset path escapes vault".to_string());
+        return Err(VaultError::EscapesVault(
+            "asset path escapes vault".to_string(),
+        ));
     }
     if !candidate.is_file() {
-        return Err("asset path is not a file".to_string());
+        return Err(VaultError::Invalid("asset path is not a file".to_string()));
     }
 
     Ok(candidate)
 }
 
-fn mime_for_path(path: &Path) -> String {
+pub(crate) fn mime_for_path(path: &Path) -> String {
     let ext = path
         .extension()
         .and_then(OsStr::to_str)
@@ -166,90 +214,368 @@ fn mime_for_path(path: &Path) -> String {
         "avif" => "image/avif",
         "bmp" => "image/bmp",
         "tif" | "tiff" => "image/tiff",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "m4a" => "audio/mp4",
+        "ogg" | "oga" => "audio/ogg",
+        "flac" => "audio/flac",
+        "webm" => "video/webm",
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "ogv" => "video/ogg",
+        "mkv" => "video/x-matroska",
         _ => "application/octet-stream",
     }
     .to_string()
 }
 
-fn resolve_note_path_for_create(vault_path: &str, rel_path: &str) -> Result<PathBuf, String> {
-    let vault =
-        std::fs::canonicalize(vault_path).map_err(|e| format!("invalid vault path: {e}"))?;
-    if !vault.is_dir() {
-        return Err("vault path is not a directory".to_string());
+/// Stable 64-bit FNV-1a hash, hex encoded. Used for cache keys that must
+/// survive restarts, so it cannot rely on `DefaultHasher`.
+pub(crate) fn content_hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
     }
+    format!("{hash:016x}")
+}
+
+/// Path for a note or folder that may not exist yet. The deepest existing
+/// ancestor is canonicalized so a symlinked parent cannot lead outside the
+/// vault; the last component is kept as given to allow case-only renames.
+fn resolve_path_for_create(vault_path: &str, rel_path: &str, kind: &str) -> VaultResult<PathBuf> {
+    let vault = resolve_vault(vault_path)?;
 
     let rel = sanitize_rel_path(rel_path)?;
     let candidate = vault.join(rel);
+    let mut base = candidate.parent().unwrap_or(&vault);
+    while !base.exists() {
+        base = base.parent().unwrap_or(&vault);
+    }
+    let rest = candidate.strip_prefix(base).unwrap_or(&candidate);
 
-    if !candidate.starts_with(&vault) {
-        return Err("note path escapes vault".to_string());
+    let base = std::fs::canonicalize(base).map_err(|e| VaultError::io(e, "invalid path"))?;
+    if !base.starts_with(&vault) {
+        return Err(VaultError::EscapesVault(format!(
+            "{kind} path escapes vault"
+        )));
     }
+    Ok(base.join(rest))
+}
+
+pub(crate) fn resolve_note_path_for_create(
+    vault_path: &str,
+    rel_path: &str,
+) -> VaultResult<PathBuf> {
+    let candidate = resolve_path_for_create(vault_path, rel_path, "note")?;
 
     if !is_markdown_file(&candidate) {
-        return Err("note is not a markdown file".to_string());
+        return Err(VaultError::NotMarkdown(
+            "note is not a markdown file".to_string(),
+        ));
     }
 
     Ok(candidate)
 }
 
-pub fn list_markdown_files_impl(vault_path: &str) -> Result<Vec<NoteEntry>, String> {
-    let vault =
-        std::fs::canonicalize(vault_path).map_err(|e| format!("invalid vault path: {e}"))?;
-    if !vault.is_dir() {
-        return Err("vault path is not a directory".to_string());
+/// Path for a folder that may not exist yet, checked like
+/// `resolve_note_path_for_create`.
+pub(crate) fn resolve_folder_path_for_create(
+    vault_path: &str,
+    rel_path: &str,
+) -> VaultResult<PathBuf> {
+    resolve_path_for_create(vault_path, rel_path, "folder")
+}
+
+/// Whether every line of the file at `path` ends in CRLF. Files with mixed
+/// endings are left to the text as given, which already carries them.
+fn uses_crlf(path: &Path) -> bool {
+    let Ok(bytes) = std::fs::read(path) else {
+        return false;
+    };
+    let mut newlines = bytes
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| **b == b'\n')
+        .peekable();
+    newlines.peek().is_some() && newlines.all(|(idx, _)| idx > 0 && bytes[idx - 1] == b'\r')
+}
+
+/// Convert editor text (always LF) back to the note's CRLF convention.
+fn to_crlf(contents: &str) -> String {
+    contents.replace("\r\n", "\n").replace('\n', "\r\n")
+}
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn write_temp(path: &Path, temp: &Path, contents: &[u8]) -> VaultResult<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(temp)
+        .map_err(|e| VaultError::io(e, "failed to create temp file"))?;
+    if let Ok(meta) = std::fs::metadata(path) {
+        file.set_permissions(meta.permissions())
+            .map_err(|e| VaultError::io(e, "failed to copy file permissions"))?;
+    }
+    file.write_all(contents)
+        .map_err(|e| VaultError::io(e, "failed to write temp file"))?;
+    file.sync_all()
+        .map_err(|e| VaultError::io(e, "failed to flush temp file"))
+}
+
+/// Write `contents` to a temp file next to `path`, fsync it, and hand it to
+/// `publish` to move into place. The temp file is always cleaned up.
+fn write_via_temp(
+    path: &Path,
+    contents: &[u8],
+    publish: impl FnOnce(&Path) -> VaultResult<()>,
+) -> VaultResult<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| VaultError::Invalid("path has no parent folder".to_string()))?;
+    let name = path.file_name().and_then(OsStr::to_str).unwrap_or("note");
+    let temp = dir.join(format!(
+        ".{name}.{}-{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = write_temp(path, &temp, contents).and_then(|()| publish(&temp));
+    // After a rename the temp file is already gone; after a hard link it is
+    // a second name for the published file.
+    let _ = std::fs::remove_file(&temp);
+    result?;
+
+    // Persist the rename itself; not supported on every platform.
+    #[cfg(unix)]
+    if let Ok(dir) = std::fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+/// Write a file through a temp file in the same folder, fsync, then rename it
+/// over the target, so a crash never leaves a truncated file behind. Existing
+/// permissions are carried over to the new file.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> VaultResult<()> {
+    write_via_temp(path, contents, |temp| {
+        std::fs::rename(temp, path).map_err(|e| VaultError::io(e, "failed to replace file"))
+    })
+}
+
+/// Like `write_atomic`, but fails with `AlreadyExists` instead of replacing a
+/// file that is already at `path`. The finished temp file is published with
+/// a hard link, which never overwrites. Where the filesystem has no hard
+/// links, `path` is reserved with `create_new` first and the temp file is
+/// renamed over that empty placeholder.
+pub(crate) fn create_atomic(path: &Path, contents: &[u8]) -> VaultResult<()> {
+    write_via_temp(path, contents, |temp| {
+        match std::fs::hard_link(temp, path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                Err(VaultError::io(e, "file already exists"))
+            }
+            Err(_) => {
+                std::fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(path)
+                    .map_err(|e| VaultError::io(e, "failed to create file"))?;
+                std::fs::rename(temp, path).map_err(|e| {
+                    let _ = std::fs::remove_file(path);
+                    VaultError::io(e, "failed to create file")
+                })
+            }
+        }
+    })
+}
+
+/// Move `source` to `dest`, failing with `AlreadyExists` if something appears
+/// at `dest` first instead of replacing it. Files are hard-linked to their
+/// new name, which never overwrites, before the old name is removed; where
+/// the filesystem has no hard links, and for folders, `dest` is checked again
+/// right before the rename. Case-only renames of the same entry are allowed.
+pub(crate) fn rename_no_replace(source: &Path, dest: &Path) -> VaultResult<()> {
+    let exists =
+        || VaultError::AlreadyExists("a note or folder already exists at this path".to_string());
+    let same_entry = std::fs::canonicalize(dest)
+        .ok()
+        .is_some_and(|dest| std::fs::canonicalize(source).ok().as_ref() == Some(&dest));
+    if same_entry {
+        return std::fs::rename(source, dest).map_err(|e| VaultError::io(e, "failed to rename"));
+    }
+
+    if source.is_file() {
+        match std::fs::hard_link(source, dest) {
+            Ok(()) => {
+                return std::fs::remove_file(source).map_err(|e| {
+                    let _ = std::fs::remove_file(dest);
+                    VaultError::io(e, "failed to rename")
+                });
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Err(exists()),
+            Err(_) => {}
+        }
+    }
+
+    if std::fs::symlink_metadata(dest).is_ok() {
+        return Err(exists());
+    }
+    std::fs::rename(source, dest).map_err(|e| {
+        // Renaming a folder onto one that appeared meanwhile fails unless it
+        // is empty; report that as the collision it is.
+        if std::fs::symlink_metadata(dest).is_ok() && std::fs::symlink_metadata(source).is_ok() {
+            exists()
+        } else {
+            VaultError::io(e, "failed to rename")
+        }
+    })
+}
+
+pub fn list_markdown_files_impl(vault_path: &str) -> VaultResult<Vec<NoteEntry>> {
+    let vault = resolve_vault(vault_path)?;
+
+    // Unreadable folders should not hide the rest of the vault.
+    let walk = walk_vault(&vault, is_markdown_file);
+    for warning in &walk.warnings {
+        log::warn!("skipped while listing notes: {warning}");
     }
 
-    let mut entries: Vec<NoteEntry> = Vec::new();
-    collect_markdown_files(&vault, &vault, &mut entries)?;
+    let mut entries = walk
+        .files
+        .iter()
+        .map(|path| {
+            Ok(NoteEntry {
+                rel_path: path_to_rel_string(&vault, path)?,
+                display_name: display_name_for_path(path),
+                metadata: None,
+            })
+        })
+        .collect::<VaultResult<Vec<NoteEntry>>>()?;
+    sort_note_entries(&mut entries);
+    Ok(entries)
+}
 
+pub(crate) fn sort_note_entries(entries: &mut [NoteEntry]) {
     entries.sort_by(|a, b| {
         a.display_name
             .to_lowercase()
             .cmp(&b.display_name.to_lowercase())
     });
-    Ok(entries)
 }
 
-pub fn read_note_impl(vault_path: &str, rel_path: &str) -> Result<String, String> {
-    let path = resolve_existing_note_path(vault_path, rel_path)?;
-    std::fs::read_to_string(path).map_err(|e| format!("failed to read note: {e}"))
+fn note_version(path: &Path, bytes: &[u8]) -> VaultResult<NoteVersion> {
+    let modified = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map_err(|e| VaultError::io(e, "failed to read modified time"))?;
+    let mtime_ms = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| VaultError::other(format!("invalid modified time: {e}")))?
+        .as_millis() as u64;
+    Ok(NoteVersion {
+        mtime_ms,
+        hash: content_hash(bytes),
+    })
+}
+
+fn read_note_contents(path: &Path) -> VaultResult<NoteContents> {
+    let bytes = std::fs::read(path).map_err(|e| VaultError::io(e, "failed to read note"))?;
+    let version = note_version(path, &bytes)?;
+    let contents = String::from_utf8(bytes)
+        .map_err(|e| VaultError::Invalid(format!("failed to read note: {e}")))?;
+    Ok(NoteContents { contents, version })
 }
 
-pub fn write_note_impl(vault_path: &str, rel_path: &str, contents: &str) -> Result<(), String> {
+pub fn read_note_impl(vault_path: &str, rel_path: &str) -> VaultResult<NoteContents> {
     let path = resolve_existing_note_path(vault_path, rel_path)?;
-    std::fs::write(path, contents).map_err(|e| format!("failed to write note: {e}"))
+    read_note_contents(&path)
 }
 
-pub fn create_note_impl(vault_path: &str, rel_path: &str, contents: &str) -> Result<(), String> {
-    let path = resolve_note_path_for_create(vault_path, rel_path)?;
-    if path.exists() {
-        if !path.is_file() {
-            return Err("note path is not a file".to_string());
-        }
-        if !is_markdown_file(&path) {
-            return Err("note is not a markdown file".to_string());
+/// Write a note, keeping its line-ending convention. When `expected` is given
+/// the write is refused if the file no longer matches that version, so edits
+/// made outside the app are never silently overwritten.
+pub fn write_note_impl(
+    vault_path: &str,
+    rel_path: &str,
+    contents: &str,
+    expected: Option<&NoteVersion>,
+) -> VaultResult<NoteVersion> {
+    let path = resolve_existing_note_path(vault_path, rel_path)?;
+
+    if let Some(expected) = expected {
+        let on_disk = read_note_contents(&path)?;
+        if on_disk.version.hash != expected.hash {
+            return Err(VaultError::Conflict(on_disk));
         }
-        return Err("note already exists".to_string());
     }
 
+    let bytes = if uses_crlf(&path) {
+        to_crlf(contents).into_bytes()
+    } else {
+        contents.as_bytes().to_vec()
+    };
+    write_atomic(&path, &bytes)?;
+    note_version(&path, &bytes)
+}
+
+pub fn create_note_impl(vault_path: &str, rel_path: &str, contents: &str) -> VaultResult<()> {
+    let path = resolve_note_path_for_create(vault_path, rel_path)?;
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)
-            .map_err(|e| format!("failed to create note folder: {e}"))?;
+            .map_err(|e| VaultError::io(e, "failed to create note folder"))?;
     }
 
-    std::fs::write(path, contents).map_err(|e| format!("failed to create note: {e}"))
+    // The note only appears once its contents are on disk, and a note
+    // created concurrently is never overwritten.
+    match create_atomic(&path, contents.as_bytes()) {
+        Err(VaultError::AlreadyExists(_)) => {
+            let message = if path.is_file() {
+                "note already exists"
+            } else {
+                "note path is not a file"
+            };
+            Err(VaultError::AlreadyExists(message.to_string()))
+        }
+        result => result,
+    }
 }
 
-pub fn read_vault_image_impl(vault_path: &str, rel_path: &str) -> Result<VaultImage, String> {
+/// Create an empty folder along with any missing parents. Returns its
+/// rel_path.
+pub fn create_folder_impl(vault_path: &str, rel_path: &str) -> VaultResult<String> {
+    let vault = resolve_vault(vault_path)?;
+    let path = resolve_folder_path_for_create(vault_path, rel_path)?;
+    if path.exists() {
+        return Err(VaultError::AlreadyExists(
+            "a note or folder already exists at this path".to_string(),
+        ));
+    }
+    let rel_path = path_to_rel_string(&vault, &path)?;
+    if is_in_trash(&rel_path) {
+        return Err(VaultError::Invalid(
+            "folders cannot be created in the trash".to_string(),
+        ));
+    }
+
+    std::fs::create_dir_all(&path).map_err(|e| VaultError::io(e, "failed to create folder"))?;
+    Ok(rel_path)
+}
+
+pub fn read_vault_image_impl(vault_path: &str, rel_path: &str) -> VaultResult<VaultImage> {
     let path = resolve_existing_asset_path(vault_path, rel_path)?;
-    let bytes = std::fs::read(&path).map_err(|e| format!("failed to read asset: {e}"))?;
-    let metadata = std::fs::metadata(&path).map_err(|e| format!("failed to read metadata: {e}"))?;
+    let bytes = std::fs::read(&path).map_err(|e| VaultError::io(e, "failed to read asset"))?;
+    let metadata =
+        std::fs::metadata(&path).map_err(|e| VaultError::io(e, "failed to read metadata"))?;
     let modified = metadata
         .modified()
-        .map_err(|e| format!("failed to read modified time: {e}"))?;
+        .map_err(|e| VaultError::io(e, "failed to read modified time"))?;
     let mtime_ms = modified
         .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| format!("invalid modified time: {e}"))?
+        .map_err(|e| VaultError::other(format!("invalid modified time: {e}")))?
         .as_millis() as u64;
 
     Ok(VaultImage {
@@ -258,3 +584,129 @@ pub fn read_vault_image_impl(vault_path: &str, rel_path: &str) -> Result<VaultIm
         mtime_ms,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        create_note_impl, read_note_impl, rename_no_replace, to_crlf, uses_crlf, write_atomic,
+        write_note_impl,
+    };
+    use crate::error::VaultError;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn converts_to_crlf() {
+        assert_eq!(to_crlf("a\nb\n"), "a\r\nb\r\n");
+        assert_eq!(to_crlf("a\r\nb\n"), "a\r\nb\r\n");
+        assert_eq!(to_crlf("single line"), "single line");
+    }
+
+    #[test]
+    fn atomic_write_replaces_contents() {
+        let dir = TempDir::new("vault-atomic");
+        let path = dir.join("Note.md");
+
+        std::fs::write(&path, "old\r\ncontents\r\n").unwrap();
+        assert!(uses_crlf(&path));
+
+        write_atomic(&path, b"new\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\n");
+        assert!(!uses_crlf(&path));
+
+        // No temp files are left behind.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn write_detects_external_edits() {
+        let dir = TempDir::new("vault-conflict");
+        std::fs::write(dir.join("Note.md"), "first").unwrap();
+        let vault = dir.to_string_lossy().to_string();
+
+        let read = read_note_impl(&vault, "Note.md").unwrap();
+        let saved = write_note_impl(&vault, "Note.md", "second", Some(&read.version)).unwrap();
+        assert_ne!(saved.hash, read.version.hash);
+
+        std::fs::write(dir.join("Note.md"), "external").unwrap();
+        match write_note_impl(&vault, "Note.md", "third", Some(&saved)) {
+            Err(VaultError::Conflict(on_disk)) => assert_eq!(on_disk.contents, "external"),
+            other => panic!("expected conflict, got {other:?}"),
+        }
+        assert_eq!(
+            std::fs::read_to_string(dir.join("Note.md")).unwrap(),
+            "external"
+        );
+    }
+
+    #[test]
+    fn create_never_overwrites() {
+        let dir = TempDir::new("vault-create");
+        std::fs::create_dir_all(dir.join("Folder.md")).unwrap();
+        let vault = dir.to_string_lossy().to_string();
+
+        create_note_impl(&vault, "sub/Note.md", "first").unwrap();
+        match create_note_impl(&vault, "sub/Note.md", "second") {
+            Err(VaultError::AlreadyExists(_)) => {}
+            other => panic!("expected already exists, got {other:?}"),
+        }
+        assert_eq!(
+            std::fs::read_to_string(dir.join("sub/Note.md")).unwrap(),
+            "first"
+        );
+        assert!(matches!(
+            create_note_impl(&vault, "Folder.md", ""),
+            Err(VaultError::AlreadyExists(_))
+        ));
+        // Neither the successful nor the refused create leaves a temp file.
+        assert_eq!(std::fs::read_dir(dir.join("sub")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn keeps_mixed_line_endings() {
+        let dir = TempDir::new("vault-line-endings");
+        let vault = dir.to_string_lossy().to_string();
+
+        std::fs::write(dir.join("Mixed.md"), "a\r\nb\nc\r\n").unwrap();
+        assert!(!uses_crlf(&dir.join("Mixed.md")));
+        write_note_impl(&vault, "Mixed.md", "a\r\nb\nc\r\nd\n", None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("Mixed.md")).unwrap(),
+            "a\r\nb\nc\r\nd\n"
+        );
+
+        std::fs::write(dir.join("Windows.md"), "a\r\nb\r\n").unwrap();
+        assert!(uses_crlf(&dir.join("Windows.md")));
+        write_note_impl(&vault, "Windows.md", "a\nb\nc\n", None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("Windows.md")).unwrap(),
+            "a\r\nb\r\nc\r\n"
+        );
+    }
+
+    #[test]
+    fn rename_never_replaces() {
+        let dir = TempDir::new("vault-rename-no-replace");
+        std::fs::write(dir.join("a.md"), "a").unwrap();
+        std::fs::write(dir.join("b.md"), "b").unwrap();
+        std::fs::create_dir_all(dir.join("from/inner")).unwrap();
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+
+        assert!(matches!(
+            rename_no_replace(&dir.join("a.md"), &dir.join("b.md")),
+            Err(VaultError::AlreadyExists(_))
+        ));
+        assert_eq!(std::fs::read_to_string(dir.join("a.md")).unwrap(), "a");
+        assert_eq!(std::fs::read_to_string(dir.join("b.md")).unwrap(), "b");
+        assert!(matches!(
+            rename_no_replace(&dir.join("from"), &dir.join("empty")),
+            Err(VaultError::AlreadyExists(_))
+        ));
+        assert!(dir.join("from/inner").is_dir());
+
+        rename_no_replace(&dir.join("a.md"), &dir.join("c.md")).unwrap();
+        assert!(!dir.join("a.md").exists());
+        assert_eq!(std::fs::read_to_string(dir.join("c.md")).unwrap(), "a");
+        rename_no_replace(&dir.join("from"), &dir.join("to")).unwrap();
+        assert!(dir.join("to/inner").is_dir());
+    }
+}
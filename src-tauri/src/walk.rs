@@ -0,0 +1,237 @@
+use crate::trash::TRASH_DIR;
+use crate::vault::{is_markdown_file, path_to_rel_string};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder, WalkState};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Vault-specific ignore file, in gitignore syntax. Takes precedence over
+/// `.gitignore` in the same folder.
+pub const IGNORE_FILE: &str = ".draglassignore";
+const GIT_IGNORE_FILE: &str = ".gitignore";
+
+/// Files found by a vault walk, plus the problems that were skipped over.
+#[derive(Debug, Default)]
+pub struct VaultWalk {
+    /// Absolute paths of accepted files, sorted
+    pub files: Vec<PathBuf>,
+    /// Absolute paths of every folder walked below the starting folder, sorted
+    pub folders: Vec<PathBuf>,
+    /// Unreadable folders, symlink loops and broken ignore files
+    pub warnings: Vec<String>,
+}
+
+/// Whether `file_name` is one of the files holding ignore rules.
+pub fn is_ignore_file(file_name: &str) -> bool {
+    file_name == IGNORE_FILE || file_name == GIT_IGNORE_FILE
+}
+
+/// Walk every file in the vault that is not ignored, in parallel, keeping
+/// those accepted by `include`. Dotfiles are walked too; hiding them is up to
+/// the caller. The trash folder and anything matched by `.draglassignore` or
+/// `.gitignore` (in any folder of the vault) are skipped. Symlinks are
+/// followed, with loops reported as warnings.
+pub fn walk_vault(vault: &Path, include: impl Fn(&Path) -> bool + Sync) -> VaultWalk {
+    walk_folder(vault, vault, None, include)
+}
+
+/// Like `walk_vault`, but only below `folder`, a path inside the vault, and
+/// at most `depth` levels deep when given. The walk still starts at the
+/// vault root so ignore files in parent folders apply.
+pub fn walk_folder(
+    vault: &Path,
+    folder: &Path,
+    depth: Option<usize>,
+    include: impl Fn(&Path) -> bool + Sync,
+) -> VaultWalk {
+    let trash = vault.join(TRASH_DIR);
+    let root = folder.to_path_buf();
+    let within_scope = move |path: &Path| {
+        if path == trash {
+            return false;
+        }
+        // Parents of the folder are only passed through.
+        if root.starts_with(path) {
+            return true;
+        }
+        match path.strip_prefix(&root) {
+            Ok(rest) => depth.map_or(true, |depth| rest.components().count() <= depth),
+            Err(_) => false,
+        }
+    };
+
+    let walker = WalkBuilder::new(vault)
+        .hidden(false)
+        .parents(false)
+        .ignore(false)
+        .git_global(false)
+        .git_exclude(false)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .follow_links(true)
+        .filter_entry(move |entry| within_scope(entry.path()))
+        .build_parallel();
+
+    let files = Mutex::new(Vec::new());
+    let folders = Mutex::new(Vec::new());
+    let warnings = Mutex::new(Vec::new());
+    let warn = |message: String| {
+        if let Ok(mut warnings) = warnings.lock() {
+            warnings.push(message);
+        }
+    };
+
+    walker.run(|| {
+        Box::new(|result| {
+            let entry = match result {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn(e.to_string());
+                    return WalkState::Continue;
+                }
+            };
+            if let Some(e) = entry.error() {
+                warn(e.to_string());
+            }
+            if !entry.path().starts_with(folder) || entry.path() == folder {
+                return WalkState::Continue;
+            }
+            let file_type = entry.file_type();
+            if file_type.is_some_and(|t| t.is_dir()) {
+                if let Ok(mut folders) = folders.lock() {
+                    folders.push(entry.into_path());
+                }
+            } else if file_type.is_some_and(|t| t.is_file()) && include(entry.path()) {
+                if let Ok(mut files) = files.lock() {
+                    files.push(entry.into_path());
+                }
+            }
+            WalkState::Continue
+        })
+    });
+
+    let mut walk = VaultWalk {
+        files: files.into_inner().unwrap_or_default(),
+        folders: folders.into_inner().unwrap_or_default(),
+        warnings: warnings.into_inner().unwrap_or_default(),
+    };
+    walk.files.sort_unstable();
+    walk.folders.sort_unstable();
+    walk.warnings.sort_unstable();
+    walk
+}
+
+/// Rules from one ignore file, or `None` when the folder has none.
+fn load_rules(dir: &Path, file_name: &str) -> Option<Gitignore> {
+    let path = dir.join(file_name);
+    if !path.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(e) = builder.add(&path) {
+        log::warn!("{e}");
+    }
+    builder.build().ok()
+}
+
+/// Whether `rel_path` is skipped by `walk_vault`. Used for single paths,
+/// such as watcher events, where walking the vault would be wasteful.
+pub fn is_ignored(vault: &Path, rel_path: &str, is_dir: bool) -> bool {
+    if rel_path == TRASH_DIR || rel_path.starts_with(&format!("{TRASH_DIR}/")) {
+        return true;
+    }
+
+    // Deeper folders override shallower ones, like in git.
+    let path = vault.join(rel_path);
+    let mut dir = path.parent();
+    while let Some(current) = dir {
+        if !current.starts_with(vault) {
+            break;
+        }
+        let relative = path.strip_prefix(current).unwrap_or(&path);
+        for file_name in [IGNORE_FILE, GIT_IGNORE_FILE] {
+            let Some(rules) = load_rules(current, file_name) else {
+                continue;
+            };
+            match rules.matched_path_or_any_parents(relative, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        dir = current.parent();
+    }
+    false
+}
+
+/// Notes now at `rel_path` or, when it is a folder, anywhere below it, as
+/// rel_paths. Lets the indexes pick up paths the app just changed without
+/// walking the whole vault.
+pub fn notes_at(vault: &Path, rel_path: &str) -> Vec<String> {
+    let path = vault.join(rel_path);
+    let is_dir = path.is_dir();
+    if is_ignored(vault, rel_path, is_dir) {
+        return Vec::new();
+    }
+    if !is_dir {
+        return if path.is_file() && is_markdown_file(&path) {
+            vec![rel_path.to_string()]
+        } else {
+            Vec::new()
+        };
+    }
+    walk_folder(vault, &path, None, is_markdown_file)
+        .files
+        .iter()
+        .filter_map(|file| path_to_rel_string(vault, file).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_ignored, walk_vault};
+    use crate::testing::TempDir;
+
+    #[test]
+    fn honours_ignore_files() {
+        let dir = TempDir::new("walk-ignore");
+        for folder in ["drafts", "notes/build", ".trash"] {
+            std::fs::create_dir_all(dir.join(folder)).unwrap();
+        }
+        std::fs::write(dir.join(".draglassignore"), "drafts/\n").unwrap();
+        std::fs::write(dir.join("notes/.gitignore"), "build/\n*.tmp.md\n").unwrap();
+        for file in [
+            "A.md",
+            "drafts/B.md",
+            "notes/C.md",
+            "notes/D.tmp.md",
+            "notes/build/E.md",
+            ".trash/F.md",
+        ] {
+            std::fs::write(dir.join(file), "x").unwrap();
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("notes/loop")).unwrap();
+
+        let walk = walk_vault(&dir, |path| path.extension().is_some_and(|e| e == "md"));
+        let found: Vec<String> = walk
+            .files
+            .iter()
+            .map(|path| {
+                path.strip_prefix(&dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        assert_eq!(found, vec!["A.md", "notes/C.md"]);
+        #[cfg(unix)]
+        assert_eq!(walk.warnings.len(), 1);
+
+        assert!(is_ignored(&dir, "drafts/B.md", false));
+        assert!(is_ignored(&dir, "notes/D.tmp.md", false));
+        assert!(is_ignored(&dir, "notes/build/E.md", false));
+        assert!(is_ignored(&dir, ".trash/F.md", false));
+        assert!(!is_ignored(&dir, "notes/C.md", false));
+    }
+}
@@ -0,0 +1,370 @@
+use crate::error::{VaultError, VaultResult};
+use crate::graph::is_hidden_path;
+use crate::vault::{is_markdown_file, path_to_rel_string, resolve_vault};
+use crate::walk::{is_ignore_file, is_ignored};
+use notify_debouncer_full::notify::event::{ModifyKind, RemoveKind, RenameMode};
+use notify_debouncer_full::notify::{EventKind, RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{
+    new_debouncer, DebounceEventResult, DebouncedEvent, Debouncer, RecommendedCache,
+};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Event emitted to the frontend whenever notes change on disk.
+pub const VAULT_CHANGED_EVENT: &str = "vault-changed";
+
+/// Quiet period before a burst of filesystem events is delivered.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// A note that moved within the vault.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RenamedNote {
+    pub from: String,
+    pub to: String,
+}
+
+/// One debounced batch of note changes, as rel_paths.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VaultChanges {
+    pub created: Vec<String>,
+    pub modified: Vec<String>,
+    pub deleted: Vec<String>,
+    pub renamed: Vec<RenamedNote>,
+    /// Set when a folder changed or events were dropped; consumers should
+    /// reload the full listing instead of trusting the per-note lists.
+    pub rescan: bool,
+}
+
+impl VaultChanges {
+    /// The changes without hidden notes, for listeners that do not show them.
+    /// A note renamed into or out of hidden paths becomes a delete or create.
+    pub fn without_hidden(self) -> VaultChanges {
+        let visible = |rel_path: &String| !is_hidden_path(rel_path);
+        let mut changes = VaultChanges {
+            created: self.created.into_iter().filter(visible).collect(),
+            modified: self.modified.into_iter().filter(visible).collect(),
+            deleted: self.deleted.into_iter().filter(visible).collect(),
+            renamed: Vec::new(),
+            rescan: self.rescan,
+        };
+        for rename in self.renamed {
+            match (visible(&rename.from), visible(&rename.to)) {
+                (true, true) => changes.renamed.push(rename),
+                (true, false) => changes.deleted.push(rename.from),
+                (false, true) => changes.created.push(rename.to),
+                (false, false) => {}
+            }
+        }
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.modified.is_empty()
+            && self.deleted.is_empty()
+            && self.renamed.is_empty()
+            && !self.rescan
+    }
+}
+
+/// Accumulates raw events for a batch before reconciling with the disk.
+#[derive(Default)]
+struct ChangeSet {
+    created: BTreeSet<String>,
+    modified: BTreeSet<String>,
+    deleted: BTreeSet<String>,
+    renamed: Vec<RenamedNote>,
+    rescan: bool,
+}
+
+/// Classified path of a raw filesystem event.
+enum EventPath {
+    Note(String),
+    Folder,
+    /// A `.gitignore` or `.draglassignore`; any change can add or drop notes
+    IgnoreRules,
+    Ignored,
+}
+
+fn classify_path(vault: &Path, path: &Path, show_hidden: bool) -> EventPath {
+    let Ok(rel_path) = path_to_rel_string(vault, path) else {
+        return EventPath::Ignored;
+    };
+    let file_name = rel_path.rsplit('/').next().unwrap_or(&rel_path);
+    if is_ignore_file(file_name) {
+        return EventPath::IgnoreRules;
+    }
+    // Hidden notes are indexed too, so they are always reported; other
+    // changes in hidden folders such as `.git` only matter when shown.
+    let is_note = is_markdown_file(path);
+    if rel_path.is_empty() || (!show_hidden && !is_note && is_hidden_path(&rel_path)) {
+        return EventPath::Ignored;
+    }
+    if is_ignored(vault, &rel_path, path.is_dir()) {
+        return EventPath::Ignored;
+    }
+    if is_note {
+        return EventPath::Note(rel_path);
+    }
+    // Removed folders can no longer be inspected; assume extensionless paths
+    // were folders so their notes are picked up by a rescan.
+    if path.is_dir() || (!path.exists() && path.extension().is_none()) {
+        return EventPath::Folder;
+    }
+    EventPath::Ignored
+}
+
+impl ChangeSet {
+    fn record(&mut self, vault: &Path, event: &DebouncedEvent, show_hidden: bool) {
+        let classify = |path: &PathBuf| classify_path(vault, path, show_hidden);
+
+        if let EventKind::Modify(ModifyKind::Name(RenameMode::Both)) = event.kind {
+            if let [from, to] = event.paths.as_slice() {
+                match (classify(from), classify(to)) {
+                    (EventPath::Note(from), EventPath::Note(to)) => {
+                        self.renamed.push(RenamedNote { from, to })
+                    }
+                    (EventPath::Note(from), _) => {
+                        self.deleted.insert(from);
+                    }
+                    (_, EventPath::Note(to)) => {
+                        self.created.insert(to);
+                    }
+                    (EventPath::Folder | EventPath::IgnoreRules, _)
+                    | (_, EventPath::Folder | EventPath::IgnoreRules) => self.rescan = true,
+                    _ => {}
+                }
+                return;
+            }
+        }
+
+        for path in &event.paths {
+            let rel_path = match classify(path) {
+                EventPath::Note(rel_path) => rel_path,
+                EventPath::Folder => {
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_)
+                            | EventKind::Remove(_)
+                            | EventKind::Modify(ModifyKind::Name(_))
+                    ) {
+                        self.rescan = true;
+                    }
+                    continue;
+                }
+                EventPath::IgnoreRules => {
+                    self.rescan = true;
+                    continue;
+                }
+                EventPath::Ignored => continue,
+            };
+
+            match event.kind {
+                EventKind::Create(_) => {
+                    self.created.insert(rel_path);
+                }
+                EventKind::Remove(RemoveKind::Folder) => self.rescan = true,
+                EventKind::Remove(_) => {
+                    self.deleted.insert(rel_path);
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                    self.deleted.insert(rel_path);
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                    self.created.insert(rel_path);
+                }
+                EventKind::Modify(ModifyKind::Name(_)) => {
+                    if path.exists() {
+                        self.created.insert(rel_path);
+                    } else {
+                        self.deleted.insert(rel_path);
+                    }
+                }
+                EventKind::Modify(ModifyKind::Metadata(_)) | EventKind::Access(_) => {}
+                EventKind::Modify(_) | EventKind::Any | EventKind::Other => {
+                    self.modified.insert(rel_path);
+                }
+            }
+        }
+    }
+
+    /// Settle the batch against what is on disk now, so short-lived files and
+    /// save-by-replace patterns collapse into a single accurate change.
+    fn finish(mut self, vault: &Path) -> VaultChanges {
+        let exists = |rel_path: &String| vault.join(rel_path).is_file();
+
+        let deleted_now: Vec<String> = self
+            .created
+            .iter()
+            .chain(self.modified.iter())
+            .filter(|rel_path| !exists(rel_path))
+            .cloned()
+            .collect();
+        let restored: Vec<String> = self
+            .deleted
+            .iter()
+            .filter(|rel_path| exists(rel_path))
+            .cloned()
+            .collect();
+
+        for rel_path in deleted_now {
+            self.modified.remove(&rel_path);
+            // A note created and removed within one batch was never visible.
+            if !self.created.remove(&rel_path) {
+                self.deleted.insert(rel_path);
+            }
+        }
+        for rel_path in restored {
+            self.deleted.remove(&rel_path);
+            self.modified.insert(rel_path);
+        }
+        for rel_path in &self.created {
+            self.modified.remove(rel_path);
+        }
+
+        VaultChanges {
+            created: self.created.into_iter().collect(),
+            modified: self.modified.into_iter().collect(),
+            deleted: self.deleted.into_iter().collect(),
+            renamed: self.renamed,
+            rescan: self.rescan,
+        }
+    }
+}
+
+struct WatchedVault {
+    root: PathBuf,
+    debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
+}
+
+/// Watches the open vault and reports debounced note changes.
+#[derive(Default)]
+pub struct VaultWatcher {
+    current: Mutex<Option<WatchedVault>>,
+}
+
+impl VaultWatcher {
+    /// Start watching a vault, replacing any previous watch. `on_change` runs on
+    /// the watcher thread with the canonical vault path and each non-empty batch.
+    /// Batches include hidden notes even when `show_hidden` is off, so indexes
+    /// stay complete; see `VaultChanges::without_hidden`.
+    pub fn watch(
+        &self,
+        vault_path: &str,
+        show_hidden: bool,
+        on_change: impl Fn(&Path, VaultChanges) + Send + 'static,
+    ) -> VaultResult<()> {
+        let vault = resolve_vault(vault_path)?;
+
+        // Drop the previous watcher first so two vaults never report at once.
+        self.unwatch();
+
+        let root = vault.clone();
+        let mut debouncer = new_debouncer(DEBOUNCE, None, move |result: DebounceEventResult| {
+            let changes = match result {
+                Ok(events) => {
+                    let mut set = ChangeSet::default();
+                    for event in &events {
+                        set.record(&root, event, show_hidden);
+                    }
+                    set.finish(&root)
+                }
+                Err(errors) => {
+                    for error in errors {
+                        log::warn!("vault watcher error: {error}");
+                    }
+                    VaultChanges {
+                        rescan: true,
+                        ..VaultChanges::default()
+                    }
+                }
+            };
+            if !changes.is_empty() {
+                on_change(&root, changes);
+            }
+        })
+        .map_err(|e| VaultError::other(format!("failed to start vault watcher: {e}")))?;
+
+        debouncer
+            .watch(&vault, RecursiveMode::Recursive)
+            .map_err(|e| VaultError::other(format!("failed to watch vault: {e}")))?;
+
+        let mut current = self
+            .current
+            .lock()
+            .map_err(|_| VaultError::other("vault watcher is unavailable"))?;
+        *current = Some(WatchedVault {
+            root: vault,
+            debouncer,
+        });
+        Ok(())
+    }
+
+    /// Stop watching the current vault, if any.
+    pub fn unwatch(&self) {
+        if let Ok(mut current) = self.current.lock() {
+            if let Some(watched) = current.take() {
+                watched.debouncer.stop_nonblocking();
+            }
+        }
+    }
+
+    /// Canonical path of the vault being watched, i.e. the one the app has
+    /// open.
+    pub fn vault(&self) -> Option<PathBuf> {
+        let current = self.current.lock().ok()?;
+        current.as_ref().map(|watched| watched.root.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChangeSet, RenamedNote, VaultChanges};
+    use crate::testing::TempDir;
+
+    #[test]
+    fn reconciles_batch_with_disk() {
+        let vault = TempDir::new("watcher-reconcile");
+        std::fs::write(vault.join("Kept.md"), "").unwrap();
+        std::fs::write(vault.join("Replaced.md"), "").unwrap();
+
+        let mut set = ChangeSet::default();
+        set.created.insert("Kept.md".into());
+        set.modified.insert("Kept.md".into());
+        set.created.insert("Transient.md".into());
+        set.modified.insert("Gone.md".into());
+        set.deleted.insert("Replaced.md".into());
+
+        let changes = set.finish(&vault);
+        assert_eq!(changes.created, vec!["Kept.md".to_string()]);
+        assert_eq!(changes.modified, vec!["Replaced.md".to_string()]);
+        assert_eq!(changes.deleted, vec!["Gone.md".to_string()]);
+    }
+
+    #[test]
+    fn hides_hidden_notes_from_listeners() {
+        let changes = VaultChanges {
+            created: vec![".drafts/New.md".into()],
+            modified: vec!["Note.md".into(), ".obsidian/Log.md".into()],
+            renamed: vec![
+                RenamedNote {
+                    from: ".drafts/Done.md".into(),
+                    to: "Done.md".into(),
+                },
+                RenamedNote {
+                    from: "Old.md".into(),
+                    to: ".archive/Old.md".into(),
+                },
+            ],
+            ..VaultChanges::default()
+        }
+        .without_hidden();
+        assert_eq!(changes.created, vec!["Done.md".to_string()]);
+        assert_eq!(changes.modified, vec!["Note.md".to_string()]);
+        assert_eq!(changes.deleted, vec!["Old.md".to_string()]);
+        assert!(changes.renamed.is_empty());
+    }
+}
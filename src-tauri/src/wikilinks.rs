@@ -0,0 +1,583 @@
+use crate::index::rel_path_key;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// A `[[wikilink]]` or `![[embed]]` in a note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wikilink {
+    /// Linked note as written, e.g. `Folder/Note`; empty for links within
+    /// the same note such as `[[#Heading]]`
+    pub target: String,
+    /// Heading after `#`, e.g. `Intro` in `[[Note#Intro]]`
+    pub heading: Option<String>,
+    /// Block id after `#^`, e.g. `abc` in `[[Note#^abc]]`
+    pub block: Option<String>,
+    /// Display text after `|`
+    pub alias: Option<String>,
+    /// Whether the link is an `![[embed]]`
+    pub is_embed: bool,
+    /// Byte range of the whole link, including `!` and brackets
+    pub range: Range<usize>,
+    /// Byte range of the target as written, before any `#` or `|`
+    pub target_range: Range<usize>,
+    /// 1-based line number
+    pub line: usize,
+}
+
+/// An inline Markdown link such as `[text](Other%20Note.md)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkdownLink {
+    /// Link text between the brackets
+    pub text: String,
+    /// Destination as written, without angle brackets or title
+    pub destination: String,
+    /// Byte range of the whole link
+    pub range: Range<usize>,
+    /// Byte range of `destination` in the note
+    pub destination_range: Range<usize>,
+    /// 1-based line number
+    pub line: usize,
+}
+
+/// Links of a note as recorded in the link index.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct NoteLinks {
+    /// Normalized note targets, deduplicated in document order. Markdown
+    /// links are resolved against the note and recorded as `/folder/note`.
+    pub notes: Vec<String>,
+    /// External URLs, deduplicated in document order
+    pub external: Vec<String>,
+}
+
+impl Wikilink {
+    /// Target normalized for resolution, e.g. `folder/note`.
+    pub fn normalized_target(&self) -> String {
+        normalize_wikilink_target(&self.target)
+    }
+}
+
+/// Normalize a link target for lookup: heading, block and alias dropped, no
+/// `.md` extension, lowercase.
+pub(crate) fn normalize_wikilink_target(target: &str) -> String {
+    let raw = target.split(['|', '#']).next().unwrap_or("");
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return String::new();
+    }
+
+    let mut s = trimmed.to_string();
+    if s.to_ascii_lowercase().ends_with(".md") {
+        s.truncate(s.len().saturating_sub(3));
+    }
+
+    s.to_ascii_lowercase()
+}
+
+/// Whether a normalized link target names a file other than a note, such as
+/// `photo.png` in `![[photo.png]]`. A dot followed by digits only, as in
+/// `[[release 1.2]]`, is not taken for an extension.
+pub(crate) fn is_attachment_target(target: &str) -> bool {
+    let name = target.rsplit('/').next().unwrap_or(target);
+    let Some((stem, ext)) = name.rsplit_once('.') else {
+        return false;
+    };
+    !stem.is_empty()
+        && ext.len() <= 5
+        && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        && ext.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// Parse the link whose `[[` starts at byte `open` of `line` and whose `]]`
+/// starts at `close`. Returns `None` for empty links such as `[[]]`.
+fn parse_link(
+    line: &str,
+    open: usize,
+    close: usize,
+    line_start: usize,
+    line_no: usize,
+) -> Option<Wikilink> {
+    let inner = &line[open + 2..close];
+    let (mut target_part, alias) = match inner.find('|') {
+        Some(pipe) => (&inner[..pipe], non_empty(&inner[pipe + 1..])),
+        None => (inner, None),
+    };
+    // Inside tables the pipe is escaped as `[[Note\|Alias]]`.
+    if alias.is_some() {
+        target_part = target_part.strip_suffix('\\').unwrap_or(target_part);
+    }
+
+    let name_len = target_part.find('#').unwrap_or(target_part.len());
+    let name = &target_part[..name_len];
+    let (heading, block) = match target_part[name_len..].strip_prefix('#') {
+        Some(fragment) => match fragment.trim().strip_prefix('^') {
+            Some(block) => (None, non_empty(block)),
+            None => (non_empty(fragment), None),
+        },
+        None => (None, None),
+    };
+    let target = name.trim().to_string();
+    if target.is_empty() && heading.is_none() && block.is_none() {
+        return None;
+    }
+
+    let is_embed = line[..open].ends_with('!');
+    let start = if is_embed { open - 1 } else { open };
+    let target_start = line_start + open + 2;
+    Some(Wikilink {
+        target,
+        heading,
+        block,
+        alias,
+        is_embed,
+        range: line_start + start..line_start + close + 2,
+        target_range: target_start..target_start + name.len(),
+        line: line_no,
+    })
+}
+
+/// Byte ranges of fenced code blocks (``` or ~~~) in `text`.
+pub(crate) fn fenced_code_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut open: Option<(usize, char, usize)> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        let fence_len = fence_char.map_or(0, |c| trimmed.chars().take_while(|x| *x == c).count());
+
+        match open {
+            None if fence_len >= 3 => open = fence_char.map(|c| (offset, c, fence_len)),
+            Some((start, c, len))
+                if fence_char == Some(c)
+                    && fence_len >= len
+                    && trimmed[fence_len..].trim().is_empty() =>
+            {
+                ranges.push((start, offset + line.len()));
+                open = None;
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    // An unclosed fence runs to the end of the note.
+    if let Some((start, _, _)) = open {
+        ranges.push((start, text.len()));
+    }
+    ranges
+}
+
+/// Byte ranges of inline code spans within a single line.
+pub(crate) fn code_span_ranges(line: &str) -> Vec<(usize, usize)> {
+    let bytes = line.as_bytes();
+    let mut ranges = Vec::new();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] != b'`' {
+            idx += 1;
+            continue;
+        }
+        let start = idx;
+        while idx < bytes.len() && bytes[idx] == b'`' {
+            idx += 1;
+        }
+        let run = idx - start;
+
+        // Find a closing run of exactly the same length.
+        let mut search = idx;
+        let mut close = None;
+        while search < bytes.len() {
+            if bytes[search] != b'`' {
+                search += 1;
+                continue;
+            }
+            let close_start = search;
+            while search < bytes.len() && bytes[search] == b'`' {
+                search += 1;
+            }
+            if search - close_start == run {
+                close = Some(search);
+                break;
+            }
+        }
+        if let Some(end) = close {
+            ranges.push((start, end));
+            idx = end;
+        }
+    }
+    ranges
+}
+
+/// Lines outside fenced code blocks as `(line number, byte offset, line)`.
+fn prose_lines(text: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    let fences = fenced_code_ranges(text);
+    let mut offset = 0;
+    text.split_inclusive('\n')
+        .enumerate()
+        .filter_map(move |(idx, line)| {
+            let line_start = offset;
+            offset += line.len();
+            let fenced = fences
+                .iter()
+                .any(|(start, end)| line_start >= *start && line_start < *end);
+            (!fenced).then_some((idx + 1, line_start, line))
+        })
+}
+
+/// Every wikilink and embed in a note, in document order. Links inside
+/// fenced code blocks, inline code and after a `\` escape are skipped, and a
+/// link never spans lines.
+pub(crate) fn parse_wikilinks(text: &str) -> Vec<Wikilink> {
+    let mut links = Vec::new();
+    for (line_no, line_start, line) in prose_lines(text) {
+        let spans = code_span_ranges(line);
+        let mut idx = 0;
+        while let Some(found) = line[idx..].find("[[") {
+            let open = idx + found;
+            let Some(close) = line[open + 2..].find("]]") else {
+                break;
+            };
+            let close = open + 2 + close;
+            idx = close + 2;
+            // A nested `[[` restarts the link, as in `[[a [[b]]`.
+            let open = line[open..close].rfind("[[").map_or(open, |i| open + i);
+
+            if line[..open].ends_with('\\') || spans.iter().any(|(s, e)| open >= *s && open < *e) {
+                continue;
+            }
+            links.extend(parse_link(line, open, close, line_start, line_no));
+        }
+    }
+    links
+}
+
+/// Split a link destination off the text following `](`, returning its
+/// offset, the destination and the number of bytes up to and including the
+/// closing `)`.
+fn parse_destination(rest: &str) -> Option<(usize, &str, usize)> {
+    let indent = rest.len() - rest.trim_start().len();
+    let body = &rest[indent..];
+    if let Some(inner) = body.strip_prefix('<') {
+        let end = inner.find('>')?;
+        let close = inner[end..].find(')')?;
+        return Some((indent + 1, &inner[..end], indent + 1 + end + close + 1));
+    }
+
+    let mut depth = 0;
+    for (idx, c) in body.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some((indent, &body[..idx], indent + idx + 1)),
+            ')' => depth -= 1,
+            // Anything after whitespace is a title such as `"Tooltip"`.
+            c if c.is_whitespace() => {
+                let close = body[idx..].find(')')?;
+                return Some((indent, &body[..idx], indent + idx + close + 1));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Every inline Markdown link in a note, in document order. Images and
+/// links inside code are skipped.
+pub(crate) fn parse_markdown_links(text: &str) -> Vec<MarkdownLink> {
+    parse_inline_links(text, false)
+}
+
+/// Every inline Markdown image (`![alt](path)`) in a note, in document
+/// order. `text` holds the alt text; the range includes the `!`.
+pub(crate) fn parse_markdown_images(text: &str) -> Vec<MarkdownLink> {
+    parse_inline_links(text, true)
+}
+
+fn parse_inline_links(text: &str, images: bool) -> Vec<MarkdownLink> {
+    let mut links = Vec::new();
+    for (line_no, line_start, line) in prose_lines(text) {
+        let spans = code_span_ranges(line);
+        let mut idx = 0;
+        while let Some(found) = line[idx..].find('[') {
+            let open = idx + found;
+            idx = open + 1;
+            let is_image = line[..open].ends_with('!');
+            let before = &line[..open - usize::from(is_image)];
+            // Escaped brackets and wikilinks are not Markdown links.
+            if is_image != images
+                || before.ends_with(['\\', '['])
+                || line[open + 1..].starts_with('[')
+                || spans.iter().any(|(s, e)| open >= *s && open < *e)
+            {
+                continue;
+            }
+
+            let mut depth = 0;
+            let close = line[open..].char_indices().find_map(|(i, c)| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                (depth == 0).then_some(open + i)
+            });
+            let Some(close) = close else {
+                continue;
+            };
+            let Some(rest) = line[close + 1..].strip_prefix('(') else {
+                continue;
+            };
+            let Some((offset, destination, len)) = parse_destination(rest) else {
+                continue;
+            };
+            if destination.is_empty() {
+                continue;
+            }
+
+            let end = close + 2 + len;
+            let destination_start = line_start + close + 2 + offset;
+            links.push(MarkdownLink {
+                text: line[open + 1..close].to_string(),
+                destination: destination.to_string(),
+                range: line_start + before.len()..line_start + end,
+                destination_range: destination_start..destination_start + destination.len(),
+                line: line_no,
+            });
+            idx = end;
+        }
+    }
+    links
+}
+
+/// Whether a link destination points outside the vault, e.g.
+/// `https://example.com` or `mailto:someone@example.com`.
+pub(crate) fn is_external_url(destination: &str) -> bool {
+    if destination.starts_with("//") {
+        return true;
+    }
+    // Require two characters so Windows drive letters are not schemes.
+    destination.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Decode `%XX` escapes; invalid escapes are kept as written.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            if let Some(byte) = s
+                .get(idx + 1..idx + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                out.push(byte);
+                idx += 3;
+                continue;
+            }
+        }
+        out.push(bytes[idx]);
+        idx += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Escape a vault path for use as a Markdown link destination. Only
+/// characters that would end or alter the destination are encoded, so the
+/// result stays readable: `My Note (draft).md` -> `My%20Note%20%28draft%29.md`.
+pub(crate) fn percent_encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' | '%' | '#' | '?' | '(' | ')' | '<' | '>' | '[' | ']' | '\\' => {
+                out.push_str(&format!("%{:02X}", c as u32));
+            }
+            c if c.is_control() => {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    out.push_str(&format!("%{byte:02X}"));
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Resolve a Markdown link destination written in the note at `rel_path`
+/// to the vault-relative path of a note. Destinations starting with `/` are
+/// relative to the vault root. Returns `None` for external URLs, links to
+/// other file types and paths escaping the vault.
+pub(crate) fn resolve_markdown_destination(rel_path: &str, destination: &str) -> Option<String> {
+    let path = resolve_destination_path(rel_path, destination)?;
+    let lower = path.to_ascii_lowercase();
+    (lower.ends_with(".md") || lower.ends_with(".markdown")).then_some(path)
+}
+
+/// Resolve a link destination written in the note at `rel_path` to a
+/// vault-relative path of any file type. Destinations starting with `/` are
+/// relative to the vault root. Returns `None` for external URLs and paths
+/// escaping the vault.
+pub(crate) fn resolve_destination_path(rel_path: &str, destination: &str) -> Option<String> {
+    if is_external_url(destination) {
+        return None;
+    }
+    let path = destination.split(['#', '?']).next().unwrap_or("");
+    let path = percent_decode(path);
+
+    let mut segments: Vec<&str> = if path.starts_with('/') {
+        Vec::new()
+    } else {
+        let mut dir: Vec<&str> = rel_path.split('/').collect();
+        dir.pop();
+        dir
+    };
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Note targets and external URLs linked from the note at `rel_path`.
+/// Links within the same note are left out.
+pub(crate) fn extract_links(rel_path: &str, text: &str) -> NoteLinks {
+    let mut links = NoteLinks::default();
+    let mut seen = HashSet::new();
+    let wikilinks = parse_wikilinks(text)
+        .into_iter()
+        .map(|link| (link.range.start, link.normalized_target()));
+    let markdown = parse_markdown_links(text).into_iter().filter_map(|link| {
+        if is_external_url(&link.destination) {
+            if !links.external.contains(&link.destination) {
+                links.external.push(link.destination);
+            }
+            return None;
+        }
+        let path = resolve_markdown_destination(rel_path, &link.destination)?;
+        Some((link.range.start, format!("/{}", rel_path_key(&path))))
+    });
+
+    let mut targets: Vec<(usize, String)> = wikilinks.chain(markdown).collect();
+    targets.sort_by_key(|(start, _)| *start);
+    for (_, target) in targets {
+        if !target.is_empty() && seen.insert(target.clone()) {
+            links.notes.push(target);
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        extract_links, normalize_wikilink_target, parse_markdown_links, parse_wikilinks,
+        resolve_markdown_destination,
+    };
+
+    fn extract_wikilinks(text: &str) -> Vec<String> {
+        extract_links("Note.md", text).notes
+    }
+
+    #[test]
+    fn normalize_wikilinks() {
+        assert_eq!(normalize_wikilink_target("  Note Name  "), "note name");
+        assert_eq!(normalize_wikilink_target(" Note | Alias "), "note");
+        assert_eq!(normalize_wikilink_target("Note#Heading"), "note");
+        assert_eq!(normalize_wikilink_target("Foo.md"), "foo");
+        assert_eq!(normalize_wikilink_target("Foo.MD"), "foo");
+        assert_eq!(normalize_wikilink_target(""), "");
+        assert_eq!(normalize_wikilink_target("folder/Note"), "folder/note");
+    }
+
+    #[test]
+    fn extracts_wikilinks() {
+        let links = extract_wikilinks("[[Foo]] [[ foo ]] [[FOO|bar]]");
+        assert_eq!(links, vec!["foo".to_string()]);
+
+        let links2 = extract_wikilinks("See [[Note A]] and [[Note B]].");
+        assert_eq!(links2, vec!["note a".to_string(), "note b".to_string()]);
+
+        let skipped = "```\n[[Fenced]]\n```\n`[[Code]]` \\[[Escaped]] [[#Local]] [[Real]]\n";
+        assert_eq!(extract_wikilinks(skipped), vec!["real".to_string()]);
+    }
+
+    #[test]
+    fn parses_link_parts() {
+        let text = "Intro\n![[Folder/Note#Part|Shown]] | [[Note#^abc1\\|x]] [[a [[b]]";
+        let links = parse_wikilinks(text);
+        assert_eq!(links.len(), 3);
+
+        let embed = &links[0];
+        assert_eq!(embed.target, "Folder/Note");
+        assert_eq!(embed.heading.as_deref(), Some("Part"));
+        assert_eq!(embed.alias.as_deref(), Some("Shown"));
+        assert!(embed.is_embed);
+        assert_eq!(embed.line, 2);
+        assert_eq!(&text[embed.range.clone()], "![[Folder/Note#Part|Shown]]");
+        assert_eq!(&text[embed.target_range.clone()], "Folder/Note");
+
+        let block = &links[1];
+        assert_eq!(block.target, "Note");
+        assert_eq!(
+            (block.heading.as_deref(), block.block.as_deref()),
+            (None, Some("abc1"))
+        );
+        assert_eq!(block.alias.as_deref(), Some("x"));
+        assert!(!block.is_embed);
+
+        assert_eq!(links[2].target, "b");
+    }
+
+    #[test]
+    fn resolves_markdown_links() {
+        let text =
+            "[Other](Other%20Note.md) [up](../projects/x.md#Intro \"Title\") ![img](a.png)\n\
+                    [site](https://example.com) [mail](mailto:a@b.c) [[Wiki]] [pdf](file.pdf)\n\
+                    `[code](code.md)` [root](</Top Level.md>) [out](../../x.md)\n";
+        let links = parse_markdown_links(text);
+        assert_eq!(links.len(), 7);
+        assert_eq!(links[0].text, "Other");
+        assert_eq!(
+            &text[links[1].range.clone()],
+            "[up](../projects/x.md#Intro \"Title\")"
+        );
+        assert_eq!(links[5].destination, "/Top Level.md");
+        assert_eq!(
+            &text[links[5].destination_range.clone()],
+            links[5].destination
+        );
+
+        assert_eq!(
+            resolve_markdown_destination("notes/a.md", "../projects/x.md#Intro"),
+            Some("projects/x.md".to_string())
+        );
+        assert_eq!(
+            resolve_markdown_destination("notes/a.md", "../../x.md"),
+            None
+        );
+
+        let extracted = extract_links("notes/a.md", text);
+        assert_eq!(
+            extracted.notes,
+            vec!["/notes/other note", "/projects/x", "wiki", "/top level"]
+        );
+        assert_eq!(
+            extracted.external,
+            vec!["https://example.com", "mailto:a@b.c"]
+        );
+    }
+}
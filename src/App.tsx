@@ -2,6 +2,7 @@ import { Suspense, lazy, useCallback, useDeferredValue, useEffect, useMemo, useR
 import './App.css'
 
 import { parseWikilinks } from './wikilinks'
+import { formatVaultError, importAttachment } from './tauri'
 import { ErrorBoundary } from './components/ErrorBoundary'
 import { FileTree } from './components/FileTree'
 import type { NoteEditorHandle } from './components/NoteEditor'
@@ -10,7 +11,7 @@ import { SettingsScreen } from './components/SettingsScreen'
 import { Toolbox } from './components/Toolbox'
 import { GraphView } from './features/graph'
 import { useSettings } from './settings'
-import { useBacklinks } from './features/backlinks/useBacklinks'
+import { splitSnippet, useBacklinks } from './features/backlinks/useBacklinks'
 import { useNoteManager } from './features/notes/useNoteManager'
 import { useRecentNotes } from './features/recents/useRecentNotes'
 import { useEditorTheme } from './features/theme/useEditorTheme'
@@ -134,6 +135,57 @@ function App() {
     [openNoteByRelPath],
   )
 
+  // Line to reveal once a note opened from a backlink has loaded.
+  const pendingRevealLineRef = useRef<number | null>(null)
+
+  const openBacklink = useCallback(
+    async (relPath: string, line: number) => {
+      if (relPath === activeRelPath && !graphViewOpen) {
+        editorRef.current?.revealLine(line)
+        return
+      }
+      pendingRevealLineRef.current = line
+      await openNoteAndCloseGraph(relPath)
+    },
+    [activeRelPath, graphViewOpen, openNoteAndCloseGraph],
+  )
+
+  useEffect(() => {
+    const line = pendingRevealLineRef.current
+    if (line == null || !activeRelPath) return
+    pendingRevealLineRef.current = null
+    queueMicrotask(() => editorRef.current?.revealLine(line))
+  }, [activeRelPath, noteText])
+
+  // Copy pasted or dropped files into the vault; returns the embeds to insert.
+  const importFilesIntoNote = useCallback(
+    async (files: File[]) => {
+      if (!vaultPath) return []
+      const folder = settings.filesAttachmentsFolder.trim() || null
+      const links: string[] = []
+      try {
+        for (const file of files) {
+          const bytes = new Uint8Array(await file.arrayBuffer())
+          const name = file.name || 'Pasted file'
+          const imported = await importAttachment(vaultPath, activeRelPath, name, bytes, folder)
+          links.push(imported.link)
+        }
+      } catch (e) {
+        setError(formatVaultError(e))
+      }
+      return links
+    },
+    [activeRelPath, settings.filesAttachmentsFolder, vaultPath],
+  )
+
+  const createNoteAndCloseGraph = useCallback(
+    (target: string) => {
+      setGraphViewOpen(false)
+      return openOrCreateWikilink(target)
+    },
+    [openOrCreateWikilink],
+  )
+
   useEffect(() => {
     const onKeyDown = (e: KeyboardEvent) => {
       if (isModShiftP(e)) {
@@ -273,6 +325,7 @@ function App() {
                 showHidden={settings.filesShowHidden}
                 theme={settings.editorTheme}
                 onOpenNote={openNoteAndCloseGraph}
+                onCreateNote={createNoteAndCloseGraph}
               />
             ) : !vaultPath ? (
               <div className="panelEmpty">Select a vault to edit notes.</div>
@@ -292,6 +345,7 @@ function App() {
                   vaultPath={vaultPath}
                   noteRelPath={activeRelPath}
                   onOpenWikilink={openOrCreateWikilink}
+                  onImportFiles={importFilesIntoNote}
                   theme={settings.editorTheme}
                 />
               </Suspense>
@@ -334,16 +388,33 @@ function App() {
                 <div className="panelEmpty">No backlinks found.</div>
               ) : (
                 <ul className="linkList">
-                  {backlinks.map((p) => (
-                    <li key={p}>
+                  {backlinks.map((b) => (
+                    <li key={b.rel_path}>
                       <button
                         className="linkItem"
                         onClick={() => {
-                          void openNoteAndCloseGraph(p)
+                          void openNoteAndCloseGraph(b.rel_path)
                         }}
                       >
-                        {p}
+                        {b.rel_path}
                       </button>
+                      {b.occurrences.map((o) => {
+                        const [before, link, after] = splitSnippet(o.snippet, o.highlight)
+                        return (
+                          <button
+                            key={o.range.start}
+                            className="backlinkSnippet"
+                            title={`Line ${o.line}`}
+                            onClick={() => {
+                              void openBacklink(b.rel_path, o.line)
+                            }}
+                          >
+                            {before}
+                            <mark>{link}</mark>
+                            {after}
+                          </button>
+                        )
+                      })}
                     </li>
                   ))}
                 </ul>
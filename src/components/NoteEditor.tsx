@@ -17,11 +17,46 @@ type NoteEditorProps = {
   vaultPath?: string | null
   noteRelPath?: string | null
   onOpenWikilink?: (rawTarget: string) => void
+  /** Store pasted or dropped files and return the links to insert for them */
+  onImportFiles?: (files: File[]) => Promise<string[]>
   theme?: 'dark' | 'light'
 }
 
+/**
+ * Hand pasted or dropped files to `onImportFiles` and insert the returned
+ * links at `pos`, or in place of the selection when `pos` is null.
+ */
+function importFiles(
+  view: EditorView,
+  event: Event,
+  files: FileList | null | undefined,
+  pos: number | null,
+  onImportFiles: NoteEditorProps['onImportFiles'],
+): boolean {
+  if (!onImportFiles || !files || files.length === 0) return false
+  event.preventDefault()
+
+  const selection = view.state.selection.main
+  const from = pos ?? selection.from
+  const to = pos ?? selection.to
+  void onImportFiles(Array.from(files)).then((links) => {
+    if (links.length === 0) return
+    // The note may have changed while the files were being copied.
+    const length = view.state.doc.length
+    const insert = links.join('\n')
+    const start = Math.min(from, length)
+    view.dispatch({
+      changes: { from: start, to: Math.min(to, length), insert },
+      selection: { anchor: start + insert.length },
+    })
+  })
+  return true
+}
+
 export type NoteEditorHandle = {
   focus: () => void
+  /** Move the cursor to the start of a 1-based line and scroll it into view */
+  revealLine: (line: number) => void
 }
 
 export const NoteEditor = forwardRef<NoteEditorHandle, NoteEditorProps>(function NoteEditor(
@@ -36,6 +71,7 @@ export const NoteEditor = forwardRef<NoteEditorHandle, NoteEditorProps>(function
     vaultPath = null,
     noteRelPath = null,
     onOpenWikilink,
+    onImportFiles,
     theme = 'dark',
   },
   ref,
@@ -75,12 +111,24 @@ export const NoteEditor = forwardRef<NoteEditorHandle, NoteEditorProps>(function
       focus: () => {
         viewRef.current?.focus()
       },
+      revealLine: (line: number) => {
+        const view = viewRef.current
+        if (!view) return
+        const doc = view.state.doc
+        const target = doc.line(Math.min(Math.max(line, 1), doc.lines))
+        view.dispatch({
+          selection: { anchor: target.from },
+          effects: EditorView.scrollIntoView(target.from, { y: 'center' }),
+        })
+        view.focus()
+      },
     }),
     [],
   )
 
   const onChangeRef = useRef<NoteEditorProps['onChange']>(onChange)
   const onSaveRequestRef = useRef<NoteEditorProps['onSaveRequest']>(onSaveRequest)
+  const onImportFilesRef = useRef<NoteEditorProps['onImportFiles']>(onImportFiles)
   const applyingExternalValueRef = useRef(false)
 
   useEffect(() => {
@@ -91,6 +139,10 @@ export const NoteEditor = forwardRef<NoteEditorHandle, NoteEditorProps>(function
     onSaveRequestRef.current = onSaveRequest
   }, [onSaveRequest])
 
+  useEffect(() => {
+    onImportFilesRef.current = onImportFiles
+  }, [onImportFiles])
+
   useEffect(() => {
     // Keep the initial doc in sync while the editor view doesn't exist yet.
     // This lets us recreate the view (e.g. when extensions change) without
@@ -217,6 +269,14 @@ export const NoteEditor = forwardRef<NoteEditorHandle, NoteEditorProps>(function
             })
           : [],
       ),
+      EditorView.domEventHandlers({
+        paste: (event, view) =>
+          importFiles(view, event, event.clipboardData?.files, null, onImportFilesRef.current),
+        drop: (event, view) => {
+          const pos = view.posAtCoords({ x: event.clientX, y: event.clientY })
+          return importFiles(view, event, event.dataTransfer?.files, pos, onImportFilesRef.current)
+        },
+      }),
       EditorView.updateListener.of((update: ViewUpdate) => {
         if (!update.docChanged) return
         if (applyingExternalValueRef.current) return
@@ -115,6 +115,15 @@ export function SettingsScreen({ open, settings, onChange, onClose, onReset }: S
                 onChange={onToggle('filesRememberExpandedFolders')}
               />
             </label>
+            <label className="settingsRow">
+              <span>Attachments folder</span>
+              <input
+                type="text"
+                placeholder="attachments"
+                value={settings.filesAttachmentsFolder}
+                onChange={(e) => onChange({ filesAttachmentsFolder: e.target.value })}
+              />
+            </label>
           </section>
 
           <section className="settingsSection">
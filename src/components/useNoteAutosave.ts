@@ -9,7 +9,8 @@ type UseNoteAutosaveArgs = {
   text: string
   isDirty: boolean
   debounceMs?: number
-  save: (vaultPath: string, relPath: string, contents: string) => Promise<void>
+  /** Resolves with the text now on disk when it differs from `contents`. */
+  save: (vaultPath: string, relPath: string, contents: string) => Promise<string | void>
   onSaved: (contents: string) => void
 }
 
@@ -87,8 +88,9 @@ export function useNoteAutosave({
         inFlightSeqRef.current = seq
         setStatus('saving')
 
+        let saved: string | void
         try {
-          await save(vaultPath, relPath, contents)
+          saved = await save(vaultPath, relPath, contents)
         } catch {
           if (keyRef.current === keyAtStart) {
             setStatus('error')
@@ -101,7 +103,7 @@ export function useNoteAutosave({
         }
 
         if (keyRef.current === keyAtStart) {
-          onSaved(contents)
+          onSaved(saved ?? contents)
           setStatus(isDirtyRef.current ? 'saving' : 'saved')
         }
 
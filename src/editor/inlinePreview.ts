@@ -8,7 +8,7 @@ import {
   WidgetType,
 } from '@codemirror/view'
 
-import { readVaultImage } from '../tauri'
+import { vaultAssetUrl } from '../tauri'
 import {
   extractImageMarkups,
   isBlockedImageTarget,
@@ -25,6 +25,8 @@ const BOLD_UNDER_RE = /__([^_]+)__/g
 const ITALIC_RE = /(^|[^*])\*([^*]+)\*(?!\*)/g
 const ITALIC_UNDER_RE = /(^|[^_])_([^_]+)_(?!_)/g
 const TASK_RE = /^\s*(?:[-+*])\s+\[( |x|X)\]/
+/** Largest side, in pixels, of images loaded for the editor preview */
+const PREVIEW_IMAGE_MAX_DIMENSION = 1600
 
 type InlineLivePreviewOptions = {
   renderImages?: boolean
@@ -35,7 +37,8 @@ type InlineLivePreviewOptions = {
 
 type ImageCacheEntry = {
   url: string
-  mtimeMs: number
+  /** ETag of the served image */
+  version: string
   mime: string
 }
 
@@ -220,18 +223,18 @@ async function loadImageAsset(
 
   const promise = (async () => {
     try {
-      const response = await readVaultImage(vaultPath, relPath)
-      const bytes = new Uint8Array(response.bytes)
-      const blob = new Blob([bytes], { type: response.mime })
+      const response = await fetch(vaultAssetUrl(vaultPath, relPath, PREVIEW_IMAGE_MAX_DIMENSION))
+      if (!response.ok) return null
+      const blob = await response.blob()
       const url = URL.createObjectURL(blob)
       const entry: ImageCacheEntry = {
         url,
-        mtimeMs: response.mtime_ms,
-        mime: response.mime,
+        version: response.headers.get('ETag') ?? '',
+        mime: blob.type,
       }
 
       const prior = cache.get(cacheKey)
-      if (prior && prior.mtimeMs === entry.mtimeMs) {
+      if (prior && prior.version === entry.version) {
         URL.revokeObjectURL(entry.url)
         return prior
       }
@@ -1,9 +1,25 @@
 import { useCallback, useEffect, useRef, useState } from 'react'
 
-import { findBacklinks } from '../../tauri'
+import { findBacklinks, formatVaultError, type Backlink } from '../../tauri'
 import { fileStem } from '../../path'
 import { normalizeWikiTarget } from '../../wikilinks'
 
+const utf8 = new TextEncoder()
+const utf8Decoder = new TextDecoder()
+
+/** Split a backlink snippet around its highlighted link (given in UTF-8 bytes). */
+export function splitSnippet(
+  snippet: string,
+  highlight: { start: number; end: number },
+): [string, string, string] {
+  const bytes = utf8.encode(snippet)
+  return [
+    utf8Decoder.decode(bytes.slice(0, highlight.start)),
+    utf8Decoder.decode(bytes.slice(highlight.start, highlight.end)),
+    utf8Decoder.decode(bytes.slice(highlight.end)),
+  ]
+}
+
 type UseBacklinksArgs = {
   enabled: boolean
   debounceMs: number
@@ -11,12 +27,12 @@ type UseBacklinksArgs = {
 }
 
 export function useBacklinks({ enabled, debounceMs, onError }: UseBacklinksArgs): {
-  backlinks: string[]
+  backlinks: Backlink[]
   backlinksBusy: boolean
   scheduleBacklinksScan: (vault: string, relPath: string) => void
   resetBacklinks: () => void
 } {
-  const [backlinks, setBacklinks] = useState<string[]>([])
+  const [backlinks, setBacklinks] = useState<Backlink[]>([])
   const [backlinksBusy, setBacklinksBusy] = useState(false)
 
   const backlinksRequestIdRef = useRef(0)
@@ -30,18 +46,18 @@ export function useBacklinks({ enabled, debounceMs, onError }: UseBacklinksArgs)
   }, [])
 
   const refreshBacklinks = useCallback(
-    async (vault: string, title: string) => {
+    async (vault: string, title: string, relPath: string) => {
       const requestId = ++backlinksRequestIdRef.current
       setBacklinksBusy(true)
       try {
-        const links = await findBacklinks(vault, title)
+        const links = await findBacklinks(vault, title, relPath)
         if (backlinksRequestIdRef.current === requestId) {
           setBacklinks(links)
         }
       } catch (e) {
         if (backlinksRequestIdRef.current === requestId) {
           setBacklinks([])
-          onError(String(e))
+          onError(formatVaultError(e))
         }
       } finally {
         if (backlinksRequestIdRef.current === requestId) {
@@ -56,13 +72,13 @@ export function useBacklinks({ enabled, debounceMs, onError }: UseBacklinksArgs)
     (vault: string, relPath: string) => {
       clearTimer()
 
-      // Backlinks are O(files) reads right now; debounce scans to avoid churn
-      // when switching notes quickly.
+      // Backlinks come from the backend link index, but each query still stats
+      // the vault; debounce scans to avoid churn when switching notes quickly.
       if (!enabled) return
       const title = normalizeWikiTarget(fileStem(relPath))
       backlinksTimerRef.current = window.setTimeout(() => {
         backlinksTimerRef.current = null
-        void refreshBacklinks(vault, title)
+        void refreshBacklinks(vault, title, relPath)
       }, debounceMs)
     },
     [clearTimer, debounceMs, enabled, refreshBacklinks],
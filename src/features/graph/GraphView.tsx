@@ -13,6 +13,8 @@ type GraphViewProps = {
   showHidden: boolean
   theme: 'dark' | 'light'
   onOpenNote: (relPath: string) => void
+  /** Create (or open) the note for an unresolved link target */
+  onCreateNote?: (target: string) => void
 }
 
 export function GraphView({
@@ -21,6 +23,7 @@ export function GraphView({
   showHidden,
   theme,
   onOpenNote,
+  onCreateNote,
 }: GraphViewProps) {
   const [settingsOpen, setSettingsOpen] = useState(false)
   const [animating, setAnimating] = useState(false)
@@ -70,12 +73,14 @@ export function GraphView({
     (nodeId: string) => {
       // Find the node to get its relPath
       const node = filteredNodes.find((n) => n.id === nodeId)
-      if (node) {
+      if (node?.kind === 'note') {
         onOpenNote(node.relPath)
+      } else if (node?.kind === 'unresolved') {
+        onCreateNote?.(node.title)
       }
       setSelectedNodeId(nodeId)
     },
-    [filteredNodes, onOpenNote, setSelectedNodeId],
+    [filteredNodes, onOpenNote, onCreateNote, setSelectedNodeId],
   )
 
   const handleNodeRightClick = useCallback(
@@ -92,7 +97,7 @@ export function GraphView({
   const handleContextMenuOpen = useCallback(() => {
     if (!contextMenu) return
     const node = filteredNodes.find((n) => n.id === contextMenu.nodeId)
-    if (node) {
+    if (node?.kind === 'note') {
       onOpenNote(node.relPath)
     }
     setContextMenu(null)
@@ -3,12 +3,19 @@
  * Node identity uses normalized relPath for consistency with backlinks and quick switcher.
  */
 
+/** What a graph node stands for; `unresolved` is a link target with no note yet */
+export type GraphNodeKind = 'note' | 'tag' | 'unresolved'
+
+/** What a graph edge stands for: a wikilink, or a note carrying a tag */
+export type GraphEdgeKind = 'link' | 'tag'
+
 export type GraphNode = {
-  /** Stable identifier - normalized relative path without .md extension */
+  /** Stable identifier - normalized relative path without .md extension, `tag:<tag>` or `unresolved:<target>` */
   id: string
-  /** Display name (filename without extension) */
+  kind: GraphNodeKind
+  /** Display name (filename without extension, `#tag`, or the missing link target) */
   title: string
-  /** Relative path including folders */
+  /** Relative path including folders (empty for tag and unresolved nodes) */
   relPath: string
   /** Whether this file matches ignore rules (hidden unless showHidden) */
   isHidden: boolean
@@ -27,6 +34,7 @@ export type GraphEdge = {
   sourceId: string
   /** Target node id (normalized relPath) */
   targetId: string
+  kind: GraphEdgeKind
   /** Number of times source links to target */
   count: number
 }
@@ -38,6 +46,10 @@ export type GraphData = {
 
 export type GraphOptions = {
   showHidden: boolean
+  /** Emit tag nodes and note→tag edges */
+  includeTags?: boolean
+  /** Emit nodes for link targets that do not exist yet */
+  includeUnresolved?: boolean
 }
 
 /** Group for coloring nodes by query match */
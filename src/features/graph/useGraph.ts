@@ -1,6 +1,6 @@
 import { useCallback, useEffect, useRef, useState } from 'react'
 
-import { buildGraph } from '../../tauri'
+import { buildGraph, formatVaultError } from '../../tauri'
 import type { GraphData, GraphEdge, GraphFilters, GraphNode, GraphScope } from './graphTypes'
 
 type UseGraphArgs = {
@@ -177,7 +177,7 @@ export function useGraph({
     } catch (e) {
       console.error('[Graph] buildGraph error:', e)
       if (requestIdRef.current === requestId) {
-        onErrorRef.current(String(e))
+        onErrorRef.current(formatVaultError(e))
         setNodes([])
         setEdges([])
       }
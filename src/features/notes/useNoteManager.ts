@@ -1,7 +1,15 @@
 import { useCallback, useMemo, useRef, useState } from 'react'
 
 import type { NoteEntry } from '../../types'
-import { createNote, readNote, writeNote } from '../../tauri'
+import {
+  createNote,
+  formatVaultError,
+  isWriteConflict,
+  mergeNoteVersions,
+  readNote,
+  writeNote,
+  type NoteVersion,
+} from '../../tauri'
 import { fileStem } from '../../path'
 import { isIgnoredPath } from '../../ignore'
 import { normalizeWikiTarget } from '../../wikilinks'
@@ -50,6 +58,12 @@ export function useNoteManager({
   const [savedText, setSavedText] = useState('')
 
   const openRequestIdRef = useRef(0)
+  // Version of the active note as last read or written, used to detect edits
+  // made outside the app before autosave overwrites them.
+  const versionRef = useRef<NoteVersion | null>(null)
+  // Text of that version: the common ancestor when merging the buffer with
+  // edits made outside the app.
+  const baseTextRef = useRef('')
 
   const noteTitle = useMemo(() => {
     if (!activeRelPath) return null
@@ -58,6 +72,43 @@ export function useNoteManager({
 
   const isDirty = noteText !== savedText
 
+  const saveNote = useCallback(
+    async (vault: string, relPath: string, contents: string): Promise<string | void> => {
+      try {
+        versionRef.current = await writeNote(vault, relPath, contents, versionRef.current)
+        baseTextRef.current = contents
+        return
+      } catch (e) {
+        if (!isWriteConflict(e)) throw e
+        const onDisk = e.on_disk
+
+        // Edits to different parts of the note are combined without asking.
+        const merge = await mergeNoteVersions(baseTextRef.current, contents, onDisk.contents)
+        if (merge.conflicts === 0) {
+          versionRef.current = await writeNote(vault, relPath, merge.merged, onDisk.version)
+          baseTextRef.current = merge.merged
+          setNoteText((current) => (current === contents ? merge.merged : current))
+          return merge.merged
+        }
+
+        const overwrite = window.confirm(
+          `"${fileStem(relPath)}" changed on disk since it was opened, and some of your edits overlap those changes.\n\n` +
+            'OK keeps your version and overwrites the file. Cancel loads the version on disk and discards your unsaved edits.',
+        )
+        if (overwrite) {
+          versionRef.current = await writeNote(vault, relPath, contents, onDisk.version)
+          baseTextRef.current = contents
+          return
+        }
+        versionRef.current = onDisk.version
+        baseTextRef.current = onDisk.contents
+        setNoteText(onDisk.contents)
+        return onDisk.contents
+      }
+    },
+    [],
+  )
+
   const autosave = useNoteAutosave({
     enabled: autosaveEnabled && !!vaultPath && !!activeRelPath,
     vaultPath,
@@ -65,7 +116,7 @@ export function useNoteManager({
     text: noteText,
     isDirty,
     debounceMs: autosaveDebounceMs,
-    save: writeNote,
+    save: saveNote,
     onSaved: setSavedText,
   })
 
@@ -73,6 +124,8 @@ export function useNoteManager({
     setActiveRelPath(null)
     setNoteText('')
     setSavedText('')
+    versionRef.current = null
+    baseTextRef.current = ''
   }, [])
 
   const openNoteByRelPath = useCallback(
@@ -89,10 +142,13 @@ export function useNoteManager({
       setError(null)
       setBusy('Opening note…')
       try {
-        const text = await readNote(vaultPath, relPath)
+        const note = await readNote(vaultPath, relPath)
 
         if (openRequestIdRef.current !== requestId) return false
 
+        const text = note.contents
+        versionRef.current = note.version
+        baseTextRef.current = text
         setActiveRelPath(relPath)
         setNoteText(text)
         setSavedText(text)
@@ -106,7 +162,7 @@ export function useNoteManager({
         return true
       } catch (e) {
         if (openRequestIdRef.current === requestId) {
-          setError(String(e))
+          setError(formatVaultError(e))
         }
         return false
       } finally {
@@ -159,7 +215,7 @@ export function useNoteManager({
         await refreshFileList(vaultPath)
         await openNoteByRelPath(relPath)
       } catch (e) {
-        setError(String(e))
+        setError(formatVaultError(e))
       } finally {
         setBusy(null)
       }
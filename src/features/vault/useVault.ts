@@ -2,7 +2,13 @@ import { useCallback, useEffect, useMemo, useRef, useState } from 'react'
 
 import { open } from '@tauri-apps/plugin-dialog'
 
-import { listMarkdownFiles } from '../../tauri'
+import {
+  formatVaultError,
+  listMarkdownFiles,
+  onVaultChanged,
+  unwatchVault,
+  watchVault,
+} from '../../tauri'
 import type { NoteEntry } from '../../types'
 import { isVisibleNoteForNavigation } from '../../ignore'
 
@@ -78,7 +84,7 @@ export function useVault({ rememberLast, showHidden, onBusy, onError }: UseVault
         await refreshFileList(vault)
       } catch (e) {
         if (loadRequestIdRef.current === requestId) {
-          onError(String(e))
+          onError(formatVaultError(e))
           setVaultPath(null)
           saveLastVaultPath(null)
         }
@@ -105,6 +111,35 @@ export function useVault({ rememberLast, showHidden, onBusy, onError }: UseVault
     return selected
   }, [loadVault, onError])
 
+  // Keep the file list in sync with changes made outside the app. Content-only
+  // edits don't change the listing, so only structural changes trigger a reload.
+  useEffect(() => {
+    if (!vaultPath) return
+    let disposed = false
+    let unlisten: (() => void) | null = null
+
+    void onVaultChanged((changes) => {
+      const structural =
+        changes.rescan ||
+        changes.created.length > 0 ||
+        changes.deleted.length > 0 ||
+        changes.renamed.length > 0
+      if (!structural) return
+      void refreshFileList(vaultPath).catch((e) => onError(formatVaultError(e)))
+    }).then((fn) => {
+      if (disposed) fn()
+      else unlisten = fn
+    })
+
+    watchVault(vaultPath, showHidden).catch((e) => onError(formatVaultError(e)))
+
+    return () => {
+      disposed = true
+      unlisten?.()
+      void unwatchVault().catch(() => {})
+    }
+  }, [onError, refreshFileList, showHidden, vaultPath])
+
   useEffect(() => {
     if (!rememberLast) return
     if (vaultPath) return
@@ -8,6 +8,8 @@ export type DraglassSettings = {
   editorTheme: 'dark' | 'light'
   filesShowHidden: boolean
   filesRememberExpandedFolders: boolean
+  /** Where pasted and dropped files go; `./` is relative to the note */
+  filesAttachmentsFolder: string
   vaultRememberLast: boolean
 
   autosaveEnabled: boolean
@@ -39,6 +41,7 @@ export const DEFAULT_SETTINGS: DraglassSettings = {
   editorTheme: 'dark',
   filesShowHidden: false,
   filesRememberExpandedFolders: true,
+  filesAttachmentsFolder: 'attachments',
   vaultRememberLast: true,
 
   autosaveEnabled: true,
@@ -62,6 +65,10 @@ function asBool(value: unknown, fallback: boolean): boolean {
   return typeof value === 'boolean' ? value : fallback
 }
 
+function asString(value: unknown, fallback: string): string {
+  return typeof value === 'string' ? value : fallback
+}
+
 function normalizeSettings(raw: unknown): DraglassSettings {
   if (!raw || typeof raw !== 'object') return DEFAULT_SETTINGS
   const r = raw as Record<string, unknown>
@@ -78,6 +85,10 @@ function normalizeSettings(raw: unknown): DraglassSettings {
       r.filesRememberExpandedFolders,
       DEFAULT_SETTINGS.filesRememberExpandedFolders,
     ),
+    filesAttachmentsFolder: asString(
+      r.filesAttachmentsFolder,
+      DEFAULT_SETTINGS.filesAttachmentsFolder,
+    ),
     vaultRememberLast: asBool(r.vaultRememberLast, DEFAULT_SETTINGS.vaultRememberLast),
 
     autosaveEnabled: asBool(r.autosaveEnabled, DEFAULT_SETTINGS.autosaveEnabled),
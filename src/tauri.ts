@@ -1,6 +1,7 @@
-import { invoke } from '@tauri-apps/api/core'
+import { convertFileSrc, invoke, type InvokeArgs, type InvokeOptions } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 
-import type { NoteEntry } from './types'
+import type { NoteEntry, NoteMetadata } from './types'
 import type { GraphData, GraphOptions } from './features/graph/graphTypes'
 
 export type VaultImageResponse = {
@@ -12,16 +13,17 @@ export type VaultImageResponse = {
 async function invokeWithFallback<T>(
   primaryCommand: string,
   fallbackCommand: string,
-  args: Record<string, unknown>,
-  fallbackArgs: Record<string, unknown>,
+  args: InvokeArgs,
+  fallbackArgs: InvokeArgs,
+  options?: InvokeOptions,
 ): Promise<T> {
   try {
-    return await invoke<T>(primaryCommand, args)
+    return await invoke<T>(primaryCommand, args, options)
   } catch (e) {
     const message = String(e)
     const notFound = /command\s+.+\s+not\s+found/i.test(message)
     if (!notFound) throw e
-    return invoke<T>(fallbackCommand, fallbackArgs)
+    return invoke<T>(fallbackCommand, fallbackArgs, options)
   }
 }
 
@@ -34,8 +36,99 @@ export async function listMarkdownFiles(vaultPath: string): Promise<NoteEntry[]>
   )
 }
 
-export async function readNote(vaultPath: string, relPath: string): Promise<string> {
-  return invokeWithFallback<string>(
+export type TreeEntry = {
+  kind: 'folder' | 'note' | 'attachment'
+  name: string
+  /** Empty for the vault root */
+  rel_path: string
+  is_hidden: boolean
+  /** Folders: total of their contents, null when part of it is not loaded */
+  size: number | null
+  mtime_ms: number | null
+  /** Notes in this folder and every subfolder */
+  note_count: number
+  /** null for files and for folders not loaded yet */
+  children: TreeEntry[] | null
+}
+
+/**
+ * List the vault (or the folder at `relPath`) as a tree. With `depth`, deeper
+ * folders come back with `children: null`; load them with another call.
+ */
+export async function listVaultTree(
+  vaultPath: string,
+  options: { relPath?: string; depth?: number; showHidden?: boolean } = {},
+): Promise<TreeEntry> {
+  const relPath = options.relPath ?? null
+  const depth = options.depth ?? null
+  const showHidden = options.showHidden ?? false
+  return invokeWithFallback<TreeEntry>(
+    'list-vault-tree',
+    'list_vault_tree',
+    { vault_path: vaultPath, rel_path: relPath, depth, show_hidden: showHidden },
+    { vaultPath, relPath, depth, showHidden },
+  )
+}
+
+export type NoteVersion = {
+  mtime_ms: number
+  hash: string
+}
+
+export type NoteContents = {
+  contents: string
+  version: NoteVersion
+}
+
+export type VaultErrorCode =
+  | 'not_found'
+  | 'already_exists'
+  | 'escapes_vault'
+  | 'not_markdown'
+  | 'permission_denied'
+  | 'conflict'
+  | 'invalid'
+  | 'io'
+
+/** Error returned by every vault command; `code` is stable, `message` is an English fallback. */
+export type VaultError =
+  | { code: 'conflict'; message: string; on_disk: NoteContents }
+  | { code: 'io'; message: string; kind: string }
+  | { code: Exclude<VaultErrorCode, 'conflict' | 'io'>; message: string }
+
+export function isVaultError(e: unknown): e is VaultError {
+  return (
+    typeof e === 'object' &&
+    e !== null &&
+    typeof (e as { code?: unknown }).code === 'string' &&
+    typeof (e as { message?: unknown }).message === 'string'
+  )
+}
+
+export function isWriteConflict(e: unknown): e is Extract<VaultError, { code: 'conflict' }> {
+  return isVaultError(e) && e.code === 'conflict'
+}
+
+const VAULT_ERROR_MESSAGES: Record<VaultErrorCode, string> = {
+  not_found: 'The note or folder no longer exists.',
+  already_exists: 'A note or folder with that name already exists.',
+  escapes_vault: 'The path points outside the vault.',
+  not_markdown: 'Only Markdown notes can be opened here.',
+  permission_denied: 'The vault cannot be accessed or is read-only.',
+  conflict: 'The note changed on disk.',
+  invalid: 'The request was not valid.',
+  io: 'A file system error occurred.',
+}
+
+/** User-facing message for any error thrown by a command. */
+export function formatVaultError(e: unknown): string {
+  if (!isVaultError(e)) return String(e)
+  const summary = VAULT_ERROR_MESSAGES[e.code] ?? e.message
+  return `${summary} (${e.message})`
+}
+
+export async function readNote(vaultPath: string, relPath: string): Promise<NoteContents> {
+  return invokeWithFallback<NoteContents>(
     'read-note',
     'read_note',
     { vault_path: vaultPath, rel_path: relPath },
@@ -47,12 +140,64 @@ export async function writeNote(
   vaultPath: string,
   relPath: string,
   contents: string,
-): Promise<void> {
-  return invokeWithFallback<void>(
+  expected: NoteVersion | null,
+): Promise<NoteVersion> {
+  return invokeWithFallback<NoteVersion>(
     'write-note',
     'write_note',
-    { vault_path: vaultPath, rel_path: relPath, contents },
-    { vaultPath, relPath, contents },
+    { vault_path: vaultPath, rel_path: relPath, contents, expected },
+    { vaultPath, relPath, contents, expected },
+  )
+}
+
+export async function getNoteMetadata(vaultPath: string, relPath: string): Promise<NoteMetadata> {
+  return invokeWithFallback<NoteMetadata>(
+    'get-note-metadata',
+    'get_note_metadata',
+    { vault_path: vaultPath, rel_path: relPath },
+    { vaultPath, relPath },
+  )
+}
+
+/** Set one frontmatter key, or remove it when `value` is `null`. */
+export async function setNoteMetadata(
+  vaultPath: string,
+  relPath: string,
+  key: string,
+  value: unknown,
+  expected: NoteVersion | null,
+): Promise<NoteVersion> {
+  return invokeWithFallback<NoteVersion>(
+    'set-note-metadata',
+    'set_note_metadata',
+    { vault_path: vaultPath, rel_path: relPath, key, value, expected },
+    { vaultPath, relPath, key, value, expected },
+  )
+}
+
+export type MergeGranularity = 'line' | 'paragraph'
+
+export type MergeHunk =
+  | { kind: 'resolved'; text: string }
+  | { kind: 'conflict'; base: string; ours: string; theirs: string }
+
+export type MergeResult = {
+  merged: string
+  hunks: MergeHunk[]
+  conflicts: number
+}
+
+export async function mergeNoteVersions(
+  base: string,
+  ours: string,
+  theirs: string,
+  granularity: MergeGranularity = 'line',
+): Promise<MergeResult> {
+  return invokeWithFallback<MergeResult>(
+    'merge-note-versions',
+    'merge_note_versions',
+    { base, ours, theirs, granularity },
+    { base, ours, theirs, granularity },
   )
 }
 
@@ -69,27 +214,431 @@ export async function createNote(
   )
 }
 
+export type RenameReport = {
+  from: string
+  to: string
+  updated: string[]
+  failed: string[]
+}
+
+export async function renameNote(
+  vaultPath: string,
+  from: string,
+  to: string,
+): Promise<RenameReport> {
+  return invokeWithFallback<RenameReport>(
+    'rename-note',
+    'rename_note',
+    { vault_path: vaultPath, from, to },
+    { vaultPath, from, to },
+  )
+}
+
+export type TrashEntry = {
+  id: string
+  original_rel_path: string
+  deleted_at_ms: number
+  is_folder: boolean
+}
+
+export type DeleteReport = {
+  entry: TrashEntry
+  linked_from: string[]
+}
+
+export async function deleteNote(vaultPath: string, relPath: string): Promise<DeleteReport> {
+  return invokeWithFallback<DeleteReport>(
+    'delete-note',
+    'delete_note',
+    { vault_path: vaultPath, rel_path: relPath },
+    { vaultPath, relPath },
+  )
+}
+
+export async function createFolder(vaultPath: string, relPath: string): Promise<string> {
+  return invokeWithFallback<string>(
+    'create-folder',
+    'create_folder',
+    { vault_path: vaultPath, rel_path: relPath },
+    { vaultPath, relPath },
+  )
+}
+
+export async function renameFolder(
+  vaultPath: string,
+  from: string,
+  to: string,
+): Promise<RenameReport> {
+  return invokeWithFallback<RenameReport>(
+    'rename-folder',
+    'rename_folder',
+    { vault_path: vaultPath, from, to },
+    { vaultPath, from, to },
+  )
+}
+
+export async function deleteFolder(vaultPath: string, relPath: string): Promise<DeleteReport> {
+  return invokeWithFallback<DeleteReport>(
+    'delete-folder',
+    'delete_folder',
+    { vault_path: vaultPath, rel_path: relPath },
+    { vaultPath, relPath },
+  )
+}
+
+export async function restoreNote(vaultPath: string, id: string): Promise<TrashEntry> {
+  return invokeWithFallback<TrashEntry>(
+    'restore-note',
+    'restore_note',
+    { vault_path: vaultPath, id },
+    { vaultPath, id },
+  )
+}
+
+export async function listTrash(vaultPath: string): Promise<TrashEntry[]> {
+  return invokeWithFallback<TrashEntry[]>(
+    'list-trash',
+    'list_trash',
+    { vault_path: vaultPath },
+    { vaultPath },
+  )
+}
+
+export async function purgeTrash(vaultPath: string, ids: string[] | null): Promise<string[]> {
+  return invokeWithFallback<string[]>(
+    'purge-trash',
+    'purge_trash',
+    { vault_path: vaultPath, ids },
+    { vaultPath, ids },
+  )
+}
+
+/**
+ * With `maxDimension`, large PNG, JPEG and WebP images come back as a cached
+ * thumbnail fitting within that many pixels on each side.
+ */
 export async function readVaultImage(
   vaultPath: string,
   relPath: string,
+  maxDimension: number | null = null,
 ): Promise<VaultImageResponse> {
   return invokeWithFallback<VaultImageResponse>(
     'read-vault-image',
     'read_vault_image',
-    { vault_path: vaultPath, rel_path: relPath },
-    { vaultPath, relPath },
+    { vault_path: vaultPath, rel_path: relPath, max_dimension: maxDimension },
+    { vaultPath, relPath, maxDimension },
+  )
+}
+
+/**
+ * URL serving a vault file through the `vault://` protocol, for `<img>`,
+ * `<audio>`, `<video>` or `fetch`. Supports range requests and revalidation;
+ * with `maxDimension`, large images are served as a cached thumbnail. Only
+ * the vault passed to `watchVault` is served.
+ */
+export function vaultAssetUrl(
+  vaultPath: string,
+  relPath: string,
+  maxDimension: number | null = null,
+): string {
+  const base = convertFileSrc('', 'vault')
+  const url = `${base}${encodeURIComponent(vaultPath)}/${encodeURIComponent(relPath)}`
+  return maxDimension == null ? url : `${url}?max_dimension=${maxDimension}`
+}
+
+export type ImportedAttachment = {
+  rel_path: string
+  /** Embed to insert into the note */
+  link: string
+}
+
+/**
+ * Copy a file into the vault's attachments folder. Folders starting with `./`
+ * are relative to the note's folder; the backend default is `attachments`.
+ * The bytes are sent as the raw request body, the rest as headers.
+ */
+export async function importAttachment(
+  vaultPath: string,
+  noteRelPath: string | null,
+  fileName: string,
+  bytes: Uint8Array,
+  folder: string | null = null,
+): Promise<ImportedAttachment> {
+  const headers: Record<string, string> = {
+    'vault-path': encodeURIComponent(vaultPath),
+    'file-name': encodeURIComponent(fileName),
+  }
+  if (noteRelPath != null) headers['note-rel-path'] = encodeURIComponent(noteRelPath)
+  if (folder != null) headers.folder = encodeURIComponent(folder)
+  return invokeWithFallback<ImportedAttachment>('import-attachment', 'import_attachment', bytes, bytes, {
+    headers,
+  })
+}
+
+export type Attachment = {
+  rel_path: string
+  size: number
+  mtime_ms: number | null
+  mime: string
+  is_hidden: boolean
+  /** Notes embedding or linking to the file */
+  referenced_by: string[]
+}
+
+export async function listAttachments(
+  vaultPath: string,
+  showHidden: boolean,
+): Promise<Attachment[]> {
+  return invokeWithFallback<Attachment[]>(
+    'list-attachments',
+    'list_attachments',
+    { vault_path: vaultPath, show_hidden: showHidden },
+    { vaultPath, showHidden },
+  )
+}
+
+export async function findOrphanAttachments(
+  vaultPath: string,
+  showHidden: boolean,
+): Promise<Attachment[]> {
+  return invokeWithFallback<Attachment[]>(
+    'find-orphan-attachments',
+    'find_orphan_attachments',
+    { vault_path: vaultPath, show_hidden: showHidden },
+    { vaultPath, showHidden },
   )
 }
 
+export type ByteRange = {
+  start: number
+  end: number
+}
+
+export type BacklinkOccurrence = {
+  /** 1-based line number of the link */
+  line: number
+  range: ByteRange
+  /** Paragraph around the link */
+  snippet: string
+  /** Position of the link within `snippet` */
+  highlight: ByteRange
+  alias: string | null
+  is_embed: boolean
+}
+
+export type Backlink = {
+  rel_path: string
+  occurrences: BacklinkOccurrence[]
+}
+
 export async function findBacklinks(
   vaultPath: string,
   targetTitle: string,
-): Promise<string[]> {
-  return invokeWithFallback<string[]>(
+  relPath: string | null = null,
+): Promise<Backlink[]> {
+  return invokeWithFallback<Backlink[]>(
     'find-backlinks',
     'find_backlinks',
-    { vault_path: vaultPath, target_title: targetTitle },
-    { vaultPath, targetTitle },
+    { vault_path: vaultPath, target_title: targetTitle, rel_path: relPath },
+    { vaultPath, targetTitle, relPath },
+  )
+}
+
+export type MentionOccurrence = {
+  /** 1-based line number of the mention */
+  line: number
+  range: ByteRange
+  /** Paragraph around the mention */
+  snippet: string
+  /** Position of the mention within `snippet` */
+  highlight: ByteRange
+}
+
+export type UnlinkedMention = {
+  rel_path: string
+  occurrences: MentionOccurrence[]
+}
+
+export async function findUnlinkedMentions(
+  vaultPath: string,
+  relPath: string,
+  showHidden: boolean,
+): Promise<UnlinkedMention[]> {
+  return invokeWithFallback<UnlinkedMention[]>(
+    'find-unlinked-mentions',
+    'find_unlinked_mentions',
+    { vault_path: vaultPath, rel_path: relPath, show_hidden: showHidden },
+    { vaultPath, relPath, showHidden },
+  )
+}
+
+/** Turn a mention found by `findUnlinkedMentions` into a wikilink to `targetRelPath`. */
+export async function linkMention(
+  vaultPath: string,
+  relPath: string,
+  range: ByteRange,
+  targetRelPath: string,
+  expected: NoteVersion | null,
+): Promise<NoteVersion> {
+  return invokeWithFallback<NoteVersion>(
+    'link-mention',
+    'link_mention',
+    {
+      vault_path: vaultPath,
+      rel_path: relPath,
+      start: range.start,
+      end: range.end,
+      target_rel_path: targetRelPath,
+      expected,
+    },
+    {
+      vaultPath,
+      relPath,
+      start: range.start,
+      end: range.end,
+      targetRelPath,
+      expected,
+    },
+  )
+}
+
+export type OutgoingLink = {
+  target: string
+  rel_path: string | null
+  external: boolean
+}
+
+export async function findOutgoingLinks(
+  vaultPath: string,
+  relPath: string,
+): Promise<OutgoingLink[]> {
+  return invokeWithFallback<OutgoingLink[]>(
+    'find-outgoing-links',
+    'find_outgoing_links',
+    { vault_path: vaultPath, rel_path: relPath },
+    { vaultPath, relPath },
+  )
+}
+
+export type AliasCollision = {
+  alias: string
+  declared_by: string[]
+  resolved_to: string
+}
+
+export async function findAliasCollisions(vaultPath: string): Promise<AliasCollision[]> {
+  return invokeWithFallback<AliasCollision[]>(
+    'find-alias-collisions',
+    'find_alias_collisions',
+    { vault_path: vaultPath },
+    { vaultPath },
+  )
+}
+
+export type UnresolvedLink = {
+  target: string
+  sources: string[]
+  count: number
+}
+
+export async function listUnresolvedLinks(
+  vaultPath: string,
+  showHidden: boolean,
+): Promise<UnresolvedLink[]> {
+  return invokeWithFallback<UnresolvedLink[]>(
+    'list-unresolved-links',
+    'list_unresolved_links',
+    { vault_path: vaultPath, show_hidden: showHidden },
+    { vaultPath, showHidden },
+  )
+}
+
+export type AmbiguousLink = {
+  /** Note containing the link */
+  source: string
+  target: string
+  /** Every note the target could mean */
+  candidates: string[]
+  /** Candidate the link resolves to, by folder proximity */
+  resolved_to: string
+}
+
+export async function listAmbiguousLinks(
+  vaultPath: string,
+  showHidden: boolean,
+): Promise<AmbiguousLink[]> {
+  return invokeWithFallback<AmbiguousLink[]>(
+    'list-ambiguous-links',
+    'list_ambiguous_links',
+    { vault_path: vaultPath, show_hidden: showHidden },
+    { vaultPath, showHidden },
+  )
+}
+
+export type SearchOptions = {
+  limit?: number
+  showHidden?: boolean
+  /** Re-scan the whole vault first, for changes the watcher missed */
+  refresh?: boolean
+}
+
+export type SearchHighlight = {
+  start: number
+  end: number
+}
+
+export type SearchSnippet = {
+  line: number
+  text: string
+  highlights: SearchHighlight[]
+}
+
+export type SearchHit = {
+  rel_path: string
+  score: number
+  snippets: SearchSnippet[]
+}
+
+export async function searchNotes(
+  vaultPath: string,
+  query: string,
+  options?: SearchOptions,
+): Promise<SearchHit[]> {
+  return invokeWithFallback<SearchHit[]>(
+    'search-notes',
+    'search_notes',
+    { vault_path: vaultPath, query, options },
+    { vaultPath, query, options },
+  )
+}
+
+export type TagNode = {
+  tag: string
+  name: string
+  count: number
+  total: number
+  children: TagNode[]
+}
+
+export async function listTags(vaultPath: string, showHidden: boolean): Promise<TagNode[]> {
+  return invokeWithFallback<TagNode[]>(
+    'list-tags',
+    'list_tags',
+    { vault_path: vaultPath, show_hidden: showHidden },
+    { vaultPath, showHidden },
+  )
+}
+
+export async function notesWithTag(
+  vaultPath: string,
+  tag: string,
+  showHidden: boolean,
+): Promise<string[]> {
+  return invokeWithFallback<string[]>(
+    'notes-with-tag',
+    'notes_with_tag',
+    { vault_path: vaultPath, tag, show_hidden: showHidden },
+    { vaultPath, tag, showHidden },
   )
 }
 
@@ -104,3 +653,35 @@ export async function buildGraph(
     { vaultPath, options },
   )
 }
+
+export type RenamedNote = {
+  from: string
+  to: string
+}
+
+export type VaultChanges = {
+  created: string[]
+  modified: string[]
+  deleted: string[]
+  renamed: RenamedNote[]
+  rescan: boolean
+}
+
+export async function watchVault(vaultPath: string, showHidden: boolean): Promise<void> {
+  return invokeWithFallback<void>(
+    'watch-vault',
+    'watch_vault',
+    { vault_path: vaultPath, show_hidden: showHidden },
+    { vaultPath, showHidden },
+  )
+}
+
+export async function unwatchVault(): Promise<void> {
+  return invokeWithFallback<void>('unwatch-vault', 'unwatch_vault', {}, {})
+}
+
+export async function onVaultChanged(
+  handler: (changes: VaultChanges) => void,
+): Promise<UnlistenFn> {
+  return listen<VaultChanges>('vault-changed', (event) => handler(event.payload))
+}
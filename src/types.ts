@@ -1,4 +1,12 @@
+export type NoteMetadata = {
+  properties: Record<string, unknown>
+  tags: string[]
+  aliases: string[]
+  errors: string[]
+}
+
 export type NoteEntry = {
   rel_path: string
   display_name: string
+  metadata?: NoteMetadata
 }